@@ -1,4 +1,6 @@
----@alias MatcherSpec { type:'regex', source:string }
+---@alias MatcherSpec { type:'regex', source:string, consume: boolean|nil, priority: number|nil, fallthrough: boolean|nil, max_fires: number|nil, expires_after_ms: number|nil }
+---@alias MatcherOptions { consume: boolean|nil, priority: number|nil, fallthrough: boolean|nil, max_fires: number|nil, expires_after_ms: number|nil }
+---@alias MultiLineMatcherOptions { consume: boolean|nil, priority: number|nil, fallthrough: boolean|nil, max_fires: number|nil, expires_after_ms: number|nil, continuation: string|nil, ['end']: string|nil, max_lines: number|nil }
 
 local M = {}
 
@@ -16,12 +18,17 @@ end
 
 ---Create a matcher using the regex syntax of the Rust lang `regex` library
 ---@param pattern string A perl-like regex pattern.
+---@param options MatcherOptions|nil
 ---@return MatcherSpec
 function M.regex(pattern, options)
   return {
     type = 'Regex',
     source = pattern,
     consume = options and options.consume,
+    priority = options and options.priority,
+    fallthrough = options and options.fallthrough,
+    max_fires = options and options.max_fires,
+    expires_after_ms = options and options.expires_after_ms,
   }
 end
 
@@ -31,11 +38,36 @@ end
 
 ---Create a matcher using "simple" syntax
 ---@param pattern string A "simple" matcher pattern
+---@param options MatcherOptions|nil
 ---@return MatcherSpec
-function M.simple(pattern)
+function M.simple(pattern, options)
   return {
     type = 'Simple',
     source = pattern,
+    priority = options and options.priority,
+    fallthrough = options and options.fallthrough,
+    max_fires = options and options.max_fires,
+    expires_after_ms = options and options.expires_after_ms,
+  }
+end
+
+---Create a matcher for a block of consecutive lines (triggers only). All patterns use
+---the same syntax as `regex()`
+---@param start string A pattern matching the first line of the block
+---@param options MultiLineMatcherOptions|nil
+---@return MatcherSpec
+function M.multi_line(start, options)
+  return {
+    type = 'MultiLine',
+    start = start,
+    continuation = options and options.continuation,
+    ['end'] = options and options['end'],
+    max_lines = options and options.max_lines,
+    consume = options and options.consume,
+    priority = options and options.priority,
+    fallthrough = options and options.fallthrough,
+    max_fires = options and options.max_fires,
+    expires_after_ms = options and options.expires_after_ms,
   }
 end
 
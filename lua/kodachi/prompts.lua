@@ -3,6 +3,8 @@ local matchers = require 'kodachi.matchers'
 
 local util = require 'kodachi.util.state'
 local with_socket = util.with_socket
+local on_matcher_registered = util.on_matcher_registered
+local replacing_matcher_id = util.replacing_matcher_id
 
 ---@class PromptGroup
 ---@field group_id number
@@ -24,20 +26,23 @@ end
 ---@param matcher MatcherSpec|string
 ---@param handler fun(context)|nil If provided, a fn called with the same params as a trigger() handler,
 ---and whose return value will be used as the prompt content
-function PromptGroup:add(matcher, handler)
+---@param opts KodachiMatcherOpts|nil
+function PromptGroup:add(matcher, handler, opts)
   matcher = matchers.inflate(matcher)
 
   local prompt_index = self._prompts:allocate_id()
 
   return with_socket(self.state, function(socket)
     if not handler then
-      socket:request {
+      socket:request({
         type = "RegisterPrompt",
         connection_id = self.state.connection_id,
         matcher = matcher,
         group_id = self.group_id,
         prompt_index = prompt_index,
-      }
+        matcher_id = replacing_matcher_id(opts),
+        group = opts and opts.group,
+      }, on_matcher_registered('prompt', opts))
       return
     end
 
@@ -52,7 +57,7 @@ function PromptGroup:add(matcher, handler)
           content = to_render,
         }
       end
-    end)
+    end, opts)
   end)
 end
 
@@ -2,8 +2,10 @@
 
 ---@alias AliasMatchedNotification { type: "'HandleAliasMatch'", id: number, connection_id: number, handler_id: number, context: table }
 ---@alias TriggerMatchedNotification { type: "'TriggerMatched'", connection_id: number, handler_id: number, context: table }
----@alias DisconnectedNotification { type: "'Disconnected'", connection_id: number  }
----@alias KodachiNotification AliasMatchedNotification | TriggerMatchedNotification | DisconnectedNotification
+---@alias DisconnectedNotification { type: "'Disconnected'", connection_id: number, reason: "'RemoteClosed'"|"'LocalDisconnect'"|"'Dns'"|"'Tls'"|"'Timeout'"|"'Io'"|"'ProcessExited'", error: string|nil, exit_code: number|nil }
+---@alias IdleNotification { type: "'Idle'", connection_id: number, seconds: number }
+---@alias MatcherExpiredNotification { type: "'MatcherExpired'", connection_id: number, kind: "'Alias'"|"'Prompt'"|"'Trigger'", matcher_id: number }
+---@alias KodachiNotification AliasMatchedNotification | TriggerMatchedNotification | DisconnectedNotification | IdleNotification | MatcherExpiredNotification
 
 local DEFAULT_BLOCKING_TIMEOUT = 500
 
@@ -5,6 +5,8 @@ local PromptsManager = require 'kodachi.prompts'
 
 local util = require 'kodachi.util.state'
 local with_socket = util.with_socket
+local on_matcher_registered = util.on_matcher_registered
+local replacing_matcher_id = util.replacing_matcher_id
 
 ---@alias KodachiEvent 'connected'|'disconnected'|'event'
 
@@ -36,6 +38,7 @@ end
 ---@field _prompts PromptsManager|nil
 ---@field _aliases Handlers|nil
 ---@field _triggers Handlers|nil
+---@field _matcher_handlers table<string, {handlers: Handlers, handler_id: number}>|nil
 ---@field _just_connected true|nil
 local KodachiState = {}
 
@@ -68,6 +71,10 @@ function KodachiState:cleanup()
     cleared_any = true
   end
 
+  if self._matcher_handlers then
+    self._matcher_handlers = {}
+  end
+
   if cleared_any and self.socket and self.connection_id then
     self.socket:notify {
       type = 'Clear',
@@ -94,7 +101,7 @@ function KodachiState:command(name, handler, opts)
   vim.api.nvim_buf_create_user_command(self.bufnr, name, callback, full_opts)
 end
 
---- @alias KodachiConnectionConfig {auto_prompts: boolean|nil}
+--- @alias KodachiConnectionConfig {auto_prompts: boolean|nil, go_ahead_prompts: boolean|nil, encoding: string|nil, environment: table<string, string>|nil, reconnect: {initial_delay_ms: number|nil, max_delay_ms: number|nil, max_attempts: number|nil}|nil, connect_timeout_ms: number|nil, keepalive: {interval_ms: number, command: string|nil}|false|nil, idle_notify_ms: number|false|nil}
 
 --- Update the connection configuration
 --- @param config KodachiConnectionConfig
@@ -160,25 +167,34 @@ function KodachiState:on(event, handler)
 end
 
 ---@param matcher MatcherSpec|string
-function KodachiState:alias(matcher, handler)
+---@param opts KodachiMatcherOpts|nil
+function KodachiState:alias(matcher, handler, opts)
   matcher = matchers.inflate(matcher)
   return with_socket(self, function(socket)
     if type(handler) == 'string' then
-      socket:request {
+      socket:request({
         type = 'RegisterAlias',
         connection_id = self.connection_id,
         matcher = matcher,
         replacement_pattern = handler,
-      }
+        matcher_id = replacing_matcher_id(opts),
+        group = opts and opts.group,
+      }, on_matcher_registered('alias', opts, function(ref)
+        self:_set_matcher_handler(ref, nil, nil)
+      end))
     else
       local aliases = self:_alias_handlers(socket)
       local id = aliases:insert(handler)
-      socket:request {
+      socket:request({
         type = 'RegisterAlias',
         connection_id = self.connection_id,
         matcher = matcher,
         handler_id = id,
-      }
+        matcher_id = replacing_matcher_id(opts),
+        group = opts and opts.group,
+      }, on_matcher_registered('alias', opts, function(ref)
+        self:_set_matcher_handler(ref, aliases, id)
+      end))
     end
   end)
 end
@@ -196,23 +212,45 @@ end
 ---@param matcher MatcherSpec|string
 ---@param handler fun(context)|nil If provided, a fn called with the same params as a trigger() handler,
 ---and whose return value will be used as the prompt content
-function KodachiState:prompt(matcher, handler)
+---@param opts KodachiMatcherOpts|nil
+function KodachiState:prompt(matcher, handler, opts)
   local prompts = self:prompts()
   local group = prompts:group(0)
-  return group:add(matcher, handler)
+  return group:add(matcher, handler, opts)
 end
 
 ---@param matcher MatcherSpec|string
-function KodachiState:trigger(matcher, handler)
+---@param opts KodachiMatcherOpts|nil
+function KodachiState:trigger(matcher, handler, opts)
   matcher = matchers.inflate(matcher)
   return with_socket(self, function(socket)
     local triggers = self:_trigger_handlers(socket)
     local id = triggers:insert(handler)
-    socket:request {
+    socket:request({
       type = 'RegisterTrigger',
       connection_id = self.connection_id,
       matcher = matcher,
       handler_id = id,
+      matcher_id = replacing_matcher_id(opts),
+      group = opts and opts.group,
+    }, on_matcher_registered('trigger', opts, function(ref)
+      self:_set_matcher_handler(ref, triggers, id)
+    end))
+  end)
+end
+
+---Unregister a single alias, trigger, or prompt, as provided to the `on_registered`
+---callback of alias(), trigger(), or prompt()
+---@param ref KodachiMatcherRef
+function KodachiState:unregister(ref)
+  return with_socket(self, function(socket)
+    self:_set_matcher_handler(ref, nil, nil)
+
+    local type_names = { alias = 'Alias', trigger = 'Trigger', prompt = 'Prompt' }
+    socket:request {
+      type = 'Unregister' .. type_names[ref.type],
+      connection_id = self.connection_id,
+      matcher_id = ref.matcher_id,
     }
   end)
 end
@@ -234,6 +272,22 @@ function KodachiState:send(text)
   end)
 end
 
+---Send an out-of-band event (IE: over GMCP or MSDP) to the connection associated with this state
+---@param ns string The protocol namespace, eg "GMCP" or "MSDP"
+---@param name string The GMCP package or MSDP variable/command name
+---@param payload any|nil
+function KodachiState:send_event(ns, name, payload)
+  return with_socket(self, function(socket)
+    socket:request {
+      type = 'SendEvent',
+      connection_id = self.connection_id,
+      ns = ns,
+      name = name,
+      payload = payload,
+    }
+  end)
+end
+
 ---@param socket Socket
 function KodachiState:_alias_handlers(socket)
   local aliases = self._aliases
@@ -294,6 +348,56 @@ function KodachiState:_trigger_handlers(socket)
   return triggers
 end
 
+---Enable or disable every alias, trigger, and prompt registered with the given `group`
+---@param group string
+---@param enabled boolean
+function KodachiState:set_group_enabled(group, enabled)
+  return with_socket(self, function(socket)
+    socket:request {
+      type = 'SetMatcherGroupEnabled',
+      connection_id = self.connection_id,
+      group = group,
+      enabled = enabled,
+    }
+  end)
+end
+
+---Track the handler associated with a registered matcher, so we can clean it up
+---when that matcher gets replaced or unregistered
+---@param ref KodachiMatcherRef
+---@param handlers Handlers|nil
+---@param handler_id number|nil
+function KodachiState:_set_matcher_handler(ref, handlers, handler_id)
+  if not self._matcher_handlers then
+    self._matcher_handlers = {}
+
+    -- The server drops matchers on its own once they've fired `max_fires` times
+    -- or after `expires_after_ms`; we just need to forget their handlers
+    self.socket:listen(function(message)
+      if message.type == 'MatcherExpired' and message.connection_id == self.connection_id then
+        vim.schedule(function()
+          self:_set_matcher_handler({
+            type = string.lower(message.kind),
+            matcher_id = message.matcher_id,
+          }, nil, nil)
+        end)
+      end
+    end)
+  end
+
+  local key = ref.type .. ':' .. ref.matcher_id
+  local previous = self._matcher_handlers[key]
+  if previous and previous.handler_id ~= handler_id then
+    previous.handlers:remove_by_id(previous.handler_id)
+  end
+
+  if handlers then
+    self._matcher_handlers[key] = { handlers = handlers, handler_id = handler_id }
+  else
+    self._matcher_handlers[key] = nil
+  end
+end
+
 function KodachiState:_state_method_call(method_call)
   return "require'kodachi.states'[" .. self.bufnr .. ']:' .. method_call
 end
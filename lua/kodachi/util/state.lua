@@ -10,4 +10,38 @@ function M.with_socket(state, handler)
   return handler(state.socket)
 end
 
+---@alias KodachiMatcherRef { type: 'alias'|'trigger'|'prompt', matcher_id: number }
+---@alias KodachiMatcherOpts { replace: KodachiMatcherRef|nil, on_registered: fun(ref: KodachiMatcherRef)|nil, group: string|nil }
+
+---@param opts KodachiMatcherOpts|nil
+---@return number|nil
+function M.replacing_matcher_id(opts)
+  return opts and opts.replace and opts.replace.matcher_id
+end
+
+---Create a response callback for a Register* request
+---@param type 'alias'|'trigger'|'prompt'
+---@param opts KodachiMatcherOpts|nil
+---@param on_ref fun(ref: KodachiMatcherRef)|nil Called before opts.on_registered
+function M.on_matcher_registered(type, opts, on_ref)
+  return function(response)
+    if response.type ~= 'MatcherRegistered' then
+      if response.type ~= 'OkResult' then
+        print('Register' .. type, ' => ', vim.inspect(response))
+      end
+      return
+    end
+
+    local ref = { type = type, matcher_id = response.matcher_id }
+    vim.schedule(function()
+      if on_ref then
+        on_ref(ref)
+      end
+      if opts and opts.on_registered then
+        opts.on_registered(ref)
+      end
+    end)
+  end
+end
+
 return M
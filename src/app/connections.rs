@@ -1,11 +1,17 @@
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use tokio::sync::mpsc;
 
-use crate::cli::ui::UiState;
+use crate::{
+    cli::ui::UiState,
+    daemon::commands::{KeepalivePolicy, ReconnectPolicy},
+    net::encoding::TextEncoding,
+    transport::EventData,
+};
 
 use super::{
     completion::completions::Completions,
@@ -16,6 +22,8 @@ use super::{
 
 pub enum Outgoing {
     Text(String),
+    Event(EventData),
+    Environment(HashMap<String, String>),
     WindowSize { width: u16, height: u16 },
     Disconnect,
 }
@@ -27,12 +35,64 @@ pub struct ConnectionState {
     pub completions: Arc<Mutex<Completions>>,
     pub sent: Arc<Mutex<History<String>>>,
     pub ui_state: Arc<Mutex<UiState>>,
+    pub encoding: Arc<Mutex<TextEncoding>>,
+
+    /// The encoding negotiated with the server (IE: via CHARSET) for the current
+    /// session, if any, which takes precedence over the configured `encoding`
+    pub negotiated_encoding: Arc<Mutex<Option<TextEncoding>>>,
+    pub environment: Arc<Mutex<HashMap<String, String>>>,
+    pub reconnect: Arc<Mutex<Option<ReconnectPolicy>>>,
+    pub connect_timeout: Arc<Mutex<Option<Duration>>>,
+    pub keepalive: Arc<Mutex<Option<KeepalivePolicy>>>,
+    pub idle_notify: Arc<Mutex<Option<Duration>>>,
+
+    /// The last window size provided by the client, if any, so it can be sent
+    /// again to a new session after reconnecting
+    pub window_size: Arc<Mutex<Option<(u16, u16)>>>,
+
+    /// Set while waiting to reconnect, when there's nowhere to send anything
+    pub reconnecting: Arc<Mutex<bool>>,
 }
 
 impl ConnectionState {
     pub fn is_auto_prompt_enabled(&self) -> bool {
         self.ui_state.lock().unwrap().is_auto_prompt_enabled
     }
+
+    pub fn is_go_ahead_prompt_enabled(&self) -> bool {
+        self.ui_state.lock().unwrap().is_go_ahead_prompt_enabled
+    }
+
+    pub fn is_server_echo_enabled(&self) -> bool {
+        self.ui_state.lock().unwrap().is_server_echo_enabled
+    }
+
+    pub fn is_reconnecting(&self) -> bool {
+        *self.reconnecting.lock().unwrap()
+    }
+
+    pub fn encoding(&self) -> TextEncoding {
+        self.negotiated_encoding
+            .lock()
+            .unwrap()
+            .unwrap_or_else(|| *self.encoding.lock().unwrap())
+    }
+
+    pub fn reconnect_policy(&self) -> Option<ReconnectPolicy> {
+        self.reconnect.lock().unwrap().clone()
+    }
+
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        *self.connect_timeout.lock().unwrap()
+    }
+
+    pub fn keepalive_policy(&self) -> Option<KeepalivePolicy> {
+        self.keepalive.lock().unwrap().clone()
+    }
+
+    pub fn idle_notify(&self) -> Option<Duration> {
+        *self.idle_notify.lock().unwrap()
+    }
 }
 
 #[derive(Clone)]
@@ -5,16 +5,44 @@ use serde::Deserialize;
 
 use crate::daemon::notifications::{MatchContext, MatchedText};
 
-use self::simple::build_simple_matcher_regex;
+use self::{
+    multi_line::{default_max_lines, BlockStep, MultiLinePattern},
+    simple::build_simple_matcher_regex,
+};
 
 use super::processing::ansi::{Ansi, AnsiStripped};
 
+pub(crate) mod multi_line;
 pub(crate) mod simple;
 
 #[derive(Debug, Default, Deserialize)]
 pub struct MatcherOptions {
     #[serde(default)]
     pub consume: bool,
+
+    /// Matchers with higher priorities are tried first. Those with the same
+    /// priority are tried in the order they were registered.
+    #[serde(default)]
+    pub priority: i32,
+
+    /// By default, once a matcher matches, no lower-priority matchers are tried.
+    /// If set, they will still be tried against whatever remains (IE: if this
+    /// matcher is `consume`, against what it didn't consume).
+    ///
+    /// NOTE: For aliases, this only applies within a single pass. An alias's
+    /// replacement is processed again from the top, so any alias (including
+    /// lower-priority ones) may still match the replacement.
+    #[serde(default)]
+    pub fallthrough: bool,
+
+    /// If set, the matcher is removed after it has matched this many times
+    #[serde(default)]
+    pub max_fires: Option<u32>,
+
+    /// If set, the matcher is removed this many milliseconds after it was
+    /// registered, whether or not it ever matched
+    #[serde(default)]
+    pub expires_after_ms: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,6 +58,24 @@ pub enum MatcherSpec {
         options: MatcherOptions,
         source: String,
     },
+
+    /// Matches a block of consecutive full lines, starting with one that matches
+    /// the `start` regex. If provided, each subsequent line must match the
+    /// `continuation` regex to be part of the block, and the block must finish
+    /// with a line that matches the `end` regex. The block may be at most
+    /// `max_lines` long; if neither `continuation` nor `end` are provided, the
+    /// block is exactly `max_lines` long.
+    /// Only supported for triggers.
+    MultiLine {
+        #[serde(flatten)]
+        options: MatcherOptions,
+        start: String,
+        continuation: Option<String>,
+        end: Option<String>,
+
+        #[serde(default = "default_max_lines")]
+        max_lines: usize,
+    },
 }
 
 pub struct MatchedResult {
@@ -47,17 +93,32 @@ pub enum MatchResult {
     Matched(MatchedResult),
 }
 
+#[derive(Debug)]
+enum Pattern {
+    Line(Regex),
+    MultiLine(MultiLinePattern),
+}
+
 #[derive(Debug)]
 pub struct Matcher {
     pub options: MatcherOptions,
-    pattern: Regex,
+    pattern: Pattern,
 }
 
 impl Matcher {
+    pub fn is_multi_line(&self) -> bool {
+        matches!(self.pattern, Pattern::MultiLine(_))
+    }
+
     pub fn try_match(&self, subject: Ansi) -> MatchResult {
+        // NOTE: Multi-line matchers never match a single line; see [Matcher::starts_block]
+        let Pattern::Line(pattern) = &self.pattern else {
+            return MatchResult::Ignored(subject);
+        };
+
         let stripped: AnsiStripped = subject.trim_trailing_newlines().strip_ansi();
-        if let Some(found) = self.pattern.captures(&stripped) {
-            let context = self.extract_match_context(&stripped, found);
+        if let Some(found) = pattern.captures(&stripped) {
+            let context = extract_match_context(pattern, &stripped, found);
 
             let mut remaining = if self.options.consume {
                 subject.without_stripped_match_range(&stripped, context.full_match_range.clone())
@@ -84,31 +145,117 @@ impl Matcher {
         MatchResult::Ignored(subject)
     }
 
-    fn extract_match_context(&self, stripped: &AnsiStripped, captures: Captures) -> MatchContext {
-        let mut named = HashMap::default();
-        let mut indexed = HashMap::default();
+    /// Returns true if this is a multi-line matcher, and `line` could be the
+    /// first line of a block
+    pub fn starts_block(&self, line: &mut Ansi) -> bool {
+        match &self.pattern {
+            Pattern::MultiLine(pattern) => {
+                let stripped = line.trim_trailing_newlines().strip_ansi();
+                pattern.start.is_match(&stripped)
+            }
+            Pattern::Line(_) => false,
+        }
+    }
+
+    /// Decide what to do with `line`, which was received while `block` was
+    /// pending for this (multi-line) matcher
+    pub fn continue_block(&self, block: &[Ansi], line: &mut Ansi) -> BlockStep {
+        match &self.pattern {
+            Pattern::MultiLine(pattern) => {
+                let stripped = line.trim_trailing_newlines().strip_ansi();
+                pattern.step(block.len(), &stripped)
+            }
+            Pattern::Line(_) => BlockStep::Abandon,
+        }
+    }
 
-        for i in 0..captures.len() {
-            let original = stripped.get_original(captures.get(i).unwrap().range());
-            indexed.insert(i, MatchedText::from(original.trim_trailing_newlines()));
+    /// Decide what to do with a pending block if output stops before it's complete
+    pub fn interrupt_block(&self) -> BlockStep {
+        match &self.pattern {
+            Pattern::MultiLine(pattern) => pattern.interrupt(),
+            Pattern::Line(_) => BlockStep::Abandon,
         }
+    }
+
+    /// Build the [MatchContext] for a completed block. The full match is every
+    /// line of the block, separated by `\n`. Indexed groups are captured from
+    /// the `start` pattern; named groups may be captured from any pattern, and
+    /// later lines take precedence.
+    pub fn block_context(&self, block: &[Ansi]) -> MatchContext {
+        let Pattern::MultiLine(pattern) = &self.pattern else {
+            panic!("Matcher ({self:?}) is unexpectedly not multi-line");
+        };
+
+        let mut context = MatchContext {
+            named: HashMap::default(),
+            indexed: HashMap::default(),
+            full_match_range: 0..0,
+        };
+
+        let mut full_match = Ansi::empty();
+        for (i, line) in block.iter().enumerate() {
+            let line = line.trim_trailing_newlines();
+            let mut stripped = line.clone();
+            let stripped = stripped.strip_ansi();
 
-        for name in self.pattern.capture_names().flatten() {
-            if let Some(captured) = captures.name(name) {
-                let original = stripped.get_original(captured.range());
-                named.insert(
-                    name.to_string(),
-                    MatchedText::from(original.trim_trailing_newlines()),
-                );
+            let line_pattern = if i == 0 {
+                Some(&pattern.start)
+            } else if i == block.len() - 1 && pattern.end.is_some() {
+                pattern.end.as_ref()
+            } else {
+                pattern.continuation.as_ref()
+            };
+            if let Some(line_pattern) = line_pattern {
+                if let Some(captures) = line_pattern.captures(&stripped) {
+                    let line_context = extract_match_context(line_pattern, &stripped, captures);
+                    context.named.extend(line_context.named);
+                    if i == 0 {
+                        context.indexed = line_context.indexed;
+                    }
+                }
+            }
+
+            if i > 0 {
+                full_match = full_match + Ansi::from("\n");
             }
+            full_match = full_match + line;
         }
 
-        MatchContext {
-            named,
-            indexed,
-            full_match_range: captures.get(0).unwrap().range(),
+        let full_match = MatchedText::from(full_match);
+        context.full_match_range = 0..full_match.plain.len();
+        context.indexed.insert(0, full_match);
+        context
+    }
+}
+
+fn extract_match_context(
+    pattern: &Regex,
+    stripped: &AnsiStripped,
+    captures: Captures,
+) -> MatchContext {
+    let mut named = HashMap::default();
+    let mut indexed = HashMap::default();
+
+    for i in 0..captures.len() {
+        let original = stripped.get_original(captures.get(i).unwrap().range());
+        indexed.insert(i, MatchedText::from(original.trim_trailing_newlines()));
+    }
+
+    for name in pattern.capture_names().flatten() {
+        if let Some(captured) = captures.name(name) {
+            let original = stripped.get_original(captured.range());
+            named.insert(
+                name.to_string(),
+                MatchedText::from(original.trim_trailing_newlines()),
+            );
         }
     }
+
+    MatchContext {
+        named,
+        indexed,
+        full_match_range: captures.get(0).unwrap().range(),
+    }
 }
 
 #[derive(Debug)]
@@ -116,24 +263,54 @@ pub enum MatcherCompileError {
     #[allow(unused)]
     SyntaxError(String),
     OutOfOrderIndexes,
+
+    /// Multi-line matchers must allow at least 2 lines
+    #[allow(unused)]
+    InvalidMaxLines(usize),
+}
+
+fn compile_regex(source: &str) -> Result<Regex, MatcherCompileError> {
+    RegexBuilder::new(source)
+        .build()
+        .map_err(|e| MatcherCompileError::SyntaxError(e.to_string()))
 }
 
 impl TryInto<Matcher> for MatcherSpec {
     type Error = MatcherCompileError;
 
     fn try_into(self) -> Result<Matcher, Self::Error> {
-        let (options, regex_source) = match self {
-            MatcherSpec::Simple { options, source } => {
-                (options, build_simple_matcher_regex(&source)?)
+        let (options, pattern) = match self {
+            MatcherSpec::Simple { options, source } => (
+                options,
+                Pattern::Line(compile_regex(&build_simple_matcher_regex(&source)?)?),
+            ),
+
+            MatcherSpec::Regex { options, source } => {
+                (options, Pattern::Line(compile_regex(&source)?))
             }
 
-            MatcherSpec::Regex { options, source } => (options, source),
+            MatcherSpec::MultiLine {
+                options,
+                start,
+                continuation,
+                end,
+                max_lines,
+            } => {
+                if max_lines < 2 {
+                    return Err(MatcherCompileError::InvalidMaxLines(max_lines));
+                }
+
+                let pattern = MultiLinePattern {
+                    start: compile_regex(&start)?,
+                    continuation: continuation.as_deref().map(compile_regex).transpose()?,
+                    end: end.as_deref().map(compile_regex).transpose()?,
+                    max_lines,
+                };
+                (options, Pattern::MultiLine(pattern))
+            }
         };
 
-        match RegexBuilder::new(&regex_source).build() {
-            Ok(pattern) => Ok(Matcher { options, pattern }),
-            Err(e) => Err(MatcherCompileError::SyntaxError(e.to_string())),
-        }
+        Ok(Matcher { options, pattern })
     }
 }
 
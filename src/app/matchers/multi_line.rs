@@ -0,0 +1,129 @@
+use regex::Regex;
+
+pub fn default_max_lines() -> usize {
+    20
+}
+
+#[derive(Debug)]
+pub struct MultiLinePattern {
+    pub start: Regex,
+    pub continuation: Option<Regex>,
+    pub end: Option<Regex>,
+    pub max_lines: usize,
+}
+
+/// What to do with a line received while a block is pending
+#[derive(Debug, PartialEq, Eq)]
+pub enum BlockStep {
+    /// The line belongs to the block, which may continue
+    Continue,
+
+    /// The block is complete. If `includes_line` the line is the last line of
+    /// the block; otherwise, the line is not part of the block at all.
+    Complete { includes_line: bool },
+
+    /// The line does not belong to the block, and the block can never be
+    /// completed
+    Abandon,
+}
+
+impl MultiLinePattern {
+    /// Decide what to do with `line`, given that the pending block already has
+    /// `block_len` lines
+    pub fn step(&self, block_len: usize, line: &str) -> BlockStep {
+        if let Some(end) = &self.end {
+            if end.is_match(line) {
+                return BlockStep::Complete {
+                    includes_line: true,
+                };
+            }
+        }
+
+        if let Some(continuation) = &self.continuation {
+            if !continuation.is_match(line) {
+                return self.interrupt();
+            }
+        }
+
+        if block_len + 1 < self.max_lines {
+            BlockStep::Continue
+        } else if self.end.is_some() {
+            // We never saw the end
+            BlockStep::Abandon
+        } else {
+            BlockStep::Complete {
+                includes_line: true,
+            }
+        }
+    }
+
+    /// Decide what to do with the pending block if output stops (IE: because
+    /// we've received a prompt) or a line doesn't match `continuation`. Blocks
+    /// that expect an `end`, or a specific number of lines, are abandoned.
+    pub fn interrupt(&self) -> BlockStep {
+        if self.end.is_none() && self.continuation.is_some() {
+            BlockStep::Complete {
+                includes_line: false,
+            }
+        } else {
+            BlockStep::Abandon
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(
+        continuation: Option<&str>,
+        end: Option<&str>,
+        max_lines: usize,
+    ) -> MultiLinePattern {
+        MultiLinePattern {
+            start: Regex::new("^Start").unwrap(),
+            continuation: continuation.map(|source| Regex::new(source).unwrap()),
+            end: end.map(|source| Regex::new(source).unwrap()),
+            max_lines,
+        }
+    }
+
+    #[test]
+    fn window_test() {
+        let pattern = pattern(None, None, 3);
+        assert_eq!(pattern.step(1, "anything"), BlockStep::Continue);
+        assert_eq!(
+            pattern.step(2, "anything"),
+            BlockStep::Complete {
+                includes_line: true
+            }
+        );
+        assert_eq!(pattern.interrupt(), BlockStep::Abandon);
+    }
+
+    #[test]
+    fn continuation_test() {
+        let pattern = pattern(Some("^  "), None, 10);
+        assert_eq!(pattern.step(1, "  indented"), BlockStep::Continue);
+        assert_eq!(
+            pattern.step(2, "Something else"),
+            BlockStep::Complete {
+                includes_line: false
+            }
+        );
+    }
+
+    #[test]
+    fn end_test() {
+        let pattern = pattern(Some("^  "), Some("^End"), 3);
+        assert_eq!(pattern.step(1, "  indented"), BlockStep::Continue);
+        assert_eq!(
+            pattern.step(2, "End"),
+            BlockStep::Complete {
+                includes_line: true
+            }
+        );
+        assert_eq!(pattern.step(2, "Something else"), BlockStep::Abandon);
+        assert_eq!(pattern.step(2, "  too long"), BlockStep::Abandon);
+    }
+}
@@ -278,9 +278,21 @@ fn strip_ansi(bytes: Bytes) -> AnsiStripped {
 
     let mut maybe_csi = false;
     let mut in_csi = false;
+    let mut in_osc = false;
+    let mut osc_escaped = false;
     let mut range_start = 0usize;
 
     for (index, ch) in raw.char_indices() {
+        if in_osc {
+            // OSC sequences (eg: hyperlinks) end with either BEL or ST (ESC \)
+            if ch == '\x07' || (osc_escaped && ch == '\\') {
+                in_osc = false;
+                ansi_ranges.push(range_start..index + 1);
+            }
+            osc_escaped = ch == '\x1b';
+            continue;
+        }
+
         match (ch as u8, maybe_csi, in_csi) {
             // ESC
             (0x1b, false, false) => {
@@ -294,6 +306,12 @@ fn strip_ansi(bytes: Bytes) -> AnsiStripped {
                 in_csi = true;
             }
 
+            (b']', true, false) => {
+                maybe_csi = false;
+                in_osc = true;
+                osc_escaped = false;
+            }
+
             // Detect ending
             (as_byte, false, true) => {
                 if (0x40..0x7E).contains(&as_byte) {
@@ -312,7 +330,7 @@ fn strip_ansi(bytes: Bytes) -> AnsiStripped {
         value: Bytes::from(without_ansi),
         original: bytes,
         ansi_ranges,
-        has_incomplete: in_csi || maybe_csi,
+        has_incomplete: in_csi || maybe_csi || in_osc,
     }
 }
 
@@ -380,6 +398,12 @@ mod tests {
         assert_eq!(&ansi.strip_ansi()[..], "Colorful");
     }
 
+    #[test]
+    fn strip_osc_hyperlinks() {
+        let mut ansi = Ansi::from("Go \x1b]8;;send:north\x1b\\north\x1b]8;;\x07!");
+        assert_eq!(&ansi.strip_ansi()[..], "Go north!");
+    }
+
     #[test]
     fn but_only_strip_ansi() {
         let mut ansi = Ansi::from("say ['anything']");
@@ -1,3 +1,6 @@
 pub mod ansi;
+pub mod msp;
+pub mod mxp;
+pub mod registry;
 pub mod send;
 pub mod text;
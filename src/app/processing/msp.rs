@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::transport::{EventData, TransportEventValue};
+
+use super::ansi::Ansi;
+
+pub const NAMESPACE: &str = "MSP";
+
+lazy_static! {
+    static ref TRIGGER_REGEX: Regex = Regex::new(r"!!(SOUND|MUSIC)\(([^)]*)\)").unwrap();
+}
+
+/// Parse the parameters of a single MSP trigger. The first parameter is always
+/// the file name; the rest are `KEY=VALUE` pairs.
+fn parse_trigger(kind: &str, params: &str) -> EventData {
+    let mut payload = HashMap::new();
+    let mut params = params.split_whitespace();
+
+    let file = params.next().unwrap_or_default();
+    payload.insert(
+        "file".to_string(),
+        TransportEventValue::String(file.to_string()),
+    );
+
+    // Fill in the defaults from the spec, so clients don't have to
+    payload.insert(
+        "volume".to_string(),
+        TransportEventValue::Number(100.into()),
+    );
+    payload.insert("repeats".to_string(), TransportEventValue::Number(1.into()));
+    if kind == "SOUND" {
+        payload.insert(
+            "priority".to_string(),
+            TransportEventValue::Number(50.into()),
+        );
+    } else {
+        payload.insert("continue".to_string(), TransportEventValue::Bool(true));
+    }
+
+    for param in params {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+
+        let number = || match value.parse::<i64>() {
+            Ok(n) => TransportEventValue::Number(n.into()),
+            Err(_) => TransportEventValue::String(value.to_string()),
+        };
+
+        let (name, value) = match key.to_ascii_uppercase().as_str() {
+            "V" => ("volume", number()),
+            "L" => ("repeats", number()),
+            "P" => ("priority", number()),
+            "C" => ("continue", TransportEventValue::Bool(value != "0")),
+            "T" => ("type", TransportEventValue::String(value.to_string())),
+            "U" => ("url", TransportEventValue::String(value.to_string())),
+            _ => continue,
+        };
+        payload.insert(name.to_string(), value);
+    }
+
+    EventData::new(
+        NAMESPACE.to_string(),
+        kind.to_string(),
+        Some(TransportEventValue::Map(payload)),
+    )
+}
+
+/// Strip any MSP triggers (IE: `!!SOUND(...)` or `!!MUSIC(...)`) from the line, returning
+/// them as events. If the line contained nothing *but* triggers, it will be emptied
+/// completely so it won't be printed.
+pub fn extract_triggers(line: &mut Ansi) -> Vec<EventData> {
+    if !line.contains("!!") {
+        return vec![];
+    }
+
+    let events: Vec<EventData> = TRIGGER_REGEX
+        .captures_iter(line)
+        .map(|captures| parse_trigger(&captures[1], &captures[2]))
+        .collect();
+
+    if !events.is_empty() {
+        let stripped = TRIGGER_REGEX.replace_all(line, "").to_string();
+        *line = if stripped.trim().is_empty() {
+            Ansi::empty()
+        } else {
+            Ansi::from(stripped)
+        };
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(event: EventData) -> HashMap<String, TransportEventValue> {
+        match event.into_payload() {
+            Some(TransportEventValue::Map(map)) => map,
+            other => panic!("Unexpected payload: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn extract_sound_test() {
+        let mut line = Ansi::from("!!SOUND(weather/thunder.wav V=80 L=2 P=90 T=weather U=https://eternia.game/sounds/)\r\n");
+        let events = extract_triggers(&mut line);
+        assert!(line.is_empty());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name(), "SOUND");
+
+        let payload = payload(events[0].clone());
+        assert_eq!(
+            payload["file"],
+            TransportEventValue::String("weather/thunder.wav".to_string())
+        );
+        assert_eq!(payload["volume"], TransportEventValue::Number(80.into()));
+        assert_eq!(payload["repeats"], TransportEventValue::Number(2.into()));
+        assert_eq!(payload["priority"], TransportEventValue::Number(90.into()));
+        assert_eq!(
+            payload["type"],
+            TransportEventValue::String("weather".to_string())
+        );
+        assert_eq!(
+            payload["url"],
+            TransportEventValue::String("https://eternia.game/sounds/".to_string())
+        );
+    }
+
+    #[test]
+    fn extract_music_defaults_test() {
+        let mut line = Ansi::from("The castle looms.!!MUSIC(grayskull.mid)\r\n");
+        let events = extract_triggers(&mut line);
+        assert_eq!(&line[..], "The castle looms.\r\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name(), "MUSIC");
+
+        let payload = payload(events[0].clone());
+        assert_eq!(payload["volume"], TransportEventValue::Number(100.into()));
+        assert_eq!(payload["repeats"], TransportEventValue::Number(1.into()));
+        assert_eq!(payload["continue"], TransportEventValue::Bool(true));
+    }
+
+    #[test]
+    fn ignore_plain_text_test() {
+        let mut line = Ansi::from("Hey!! Listen!\r\n");
+        let events = extract_triggers(&mut line);
+        assert!(events.is_empty());
+        assert_eq!(&line[..], "Hey!! Listen!\r\n");
+    }
+}
@@ -0,0 +1,839 @@
+use std::collections::HashMap;
+
+use crate::daemon::notifications::external_ui::{TextLink, TextLinkAction};
+
+use super::ansi::Ansi;
+
+const OSC_HYPERLINK: &str = "\x1b]8;";
+const STRING_TERMINATOR: &str = "\x1b\\";
+
+const SEND_SCHEME: &str = "send:";
+
+/// How MXP tags on a line should be treated. See the "Line Tags" section of the
+/// MXP spec: https://www.zuggsoft.com/zmud/mxp.htm
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum LineMode {
+    /// Only formatting ("open") tags are allowed
+    #[default]
+    Open,
+
+    /// All tags are allowed
+    Secure,
+
+    /// Tags are not parsed at all
+    Locked,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum OpenTag {
+    Format {
+        name: &'static str,
+        reset: &'static str,
+    },
+    Color {
+        name: &'static str,
+        sgr: String,
+    },
+    Link {
+        name: &'static str,
+        start: usize,
+        href: Option<String>,
+        hint: Option<String>,
+        prompt: bool,
+        is_send: bool,
+        content: String,
+    },
+}
+
+impl OpenTag {
+    fn name(&self) -> &'static str {
+        match self {
+            OpenTag::Format { name, .. } => name,
+            OpenTag::Color { name, .. } => name,
+            OpenTag::Link { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Tag {
+    name: String,
+    closing: bool,
+    positional: Vec<String>,
+    attrs: HashMap<String, String>,
+}
+
+impl Tag {
+    fn parse(raw: &str) -> Option<Tag> {
+        let raw = raw.trim();
+        if raw.starts_with('!') {
+            // Definitions and comments; we don't support custom elements (yet?)
+            return None;
+        }
+
+        let (closing, raw) = match raw.strip_prefix('/') {
+            Some(raw) => (true, raw.trim_start()),
+            None => (false, raw),
+        };
+        let raw = raw.strip_suffix('/').unwrap_or(raw);
+
+        let mut tokens = tokenize(raw).into_iter();
+        let mut tag = Tag {
+            name: tokens.next()?.to_ascii_lowercase(),
+            closing,
+            ..Default::default()
+        };
+
+        for token in tokens {
+            match token.split_once('=') {
+                Some((key, value))
+                    if !key.is_empty()
+                        && key
+                            .chars()
+                            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-') =>
+                {
+                    tag.attrs
+                        .insert(key.to_ascii_lowercase(), value.to_string());
+                }
+                _ => tag.positional.push(token),
+            }
+        }
+
+        Some(tag)
+    }
+
+    /// Look up a parameter, either by its name or its position
+    fn param(&self, name: &str, position: usize) -> Option<&str> {
+        self.attrs
+            .get(name)
+            .or_else(|| {
+                self.positional
+                    .iter()
+                    .filter(|p| !p.eq_ignore_ascii_case("prompt"))
+                    .nth(position)
+            })
+            .map(|s| s.as_str())
+    }
+
+    fn has_flag(&self, name: &str) -> bool {
+        self.attrs.contains_key(name)
+            || self.positional.iter().any(|p| p.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Split tag contents by whitespace, respecting (and removing) quotes
+fn tokenize(raw: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut has_token = false;
+
+    for ch in raw.chars() {
+        match (quote, ch) {
+            (Some(q), ch) if ch == q => quote = None,
+            (Some(_), ch) => current.push(ch),
+            (None, '"' | '\'') => {
+                quote = Some(ch);
+                has_token = true;
+            }
+            (None, ch) if ch.is_whitespace() => {
+                if has_token || !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            (None, ch) => current.push(ch),
+        }
+    }
+
+    if has_token || !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Find the end of a tag starting at the beginning of `text`, respecting quotes
+fn find_tag_end(text: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (index, ch) in text.char_indices().skip(1) {
+        match (quote, ch) {
+            (Some(q), ch) if ch == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '>') => return Some(index),
+            (None, '<') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// If `text` starts with a CSI sequence, return its length
+fn csi_len(text: &str) -> Option<usize> {
+    let params = text.strip_prefix("\x1b[")?;
+    let end = params.find(|ch: char| ('\x40'..'\x7e').contains(&ch))?;
+    Some(2 + end + 1)
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "amp" => Some('&'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Parse an MXP line mode sequence (`ESC [ <n> z`), returning the mode number and
+/// the length of the sequence
+fn parse_line_mode(text: &str) -> Option<(u32, usize)> {
+    let rest = text.strip_prefix("\x1b[")?;
+    let digits = rest.find(|ch: char| !ch.is_ascii_digit())?;
+    if digits == 0 || !rest[digits..].starts_with('z') {
+        return None;
+    }
+    let mode = rest[..digits].parse().ok()?;
+    Some((mode, 2 + digits + 1))
+}
+
+const NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("black", (0, 0, 0)),
+    ("red", (255, 0, 0)),
+    ("green", (0, 128, 0)),
+    ("lime", (0, 255, 0)),
+    ("yellow", (255, 255, 0)),
+    ("blue", (0, 0, 255)),
+    ("magenta", (255, 0, 255)),
+    ("fuchsia", (255, 0, 255)),
+    ("cyan", (0, 255, 255)),
+    ("aqua", (0, 255, 255)),
+    ("white", (255, 255, 255)),
+    ("gray", (128, 128, 128)),
+    ("grey", (128, 128, 128)),
+    ("silver", (192, 192, 192)),
+    ("maroon", (128, 0, 0)),
+    ("olive", (128, 128, 0)),
+    ("navy", (0, 0, 128)),
+    ("purple", (128, 0, 128)),
+    ("teal", (0, 128, 128)),
+    ("orange", (255, 165, 0)),
+    ("brown", (165, 42, 42)),
+    ("pink", (255, 192, 203)),
+    ("gold", (255, 215, 0)),
+];
+
+fn parse_color(value: &str) -> Option<(u8, u8, u8)> {
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some((component(0)?, component(2)?, component(4)?));
+    }
+
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, rgb)| *rgb)
+}
+
+fn color_sgr(fore: Option<&str>, back: Option<&str>) -> Option<String> {
+    let mut params = Vec::new();
+    if let Some((r, g, b)) = fore.and_then(parse_color) {
+        params.push(format!("38;2;{r};{g};{b}"));
+    }
+    if let Some((r, g, b)) = back.and_then(parse_color) {
+        params.push(format!("48;2;{r};{g};{b}"));
+    }
+
+    if params.is_empty() {
+        None
+    } else {
+        Some(format!("\x1b[{}m", params.join(";")))
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~' | b' ') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Tracks MXP state for a connection, and converts MXP tags into ANSI sequences.
+/// Links (`<send>` and `<a>`) are converted into OSC 8 hyperlinks, which can be
+/// extracted into structured spans via [extract_links].
+#[derive(Clone, Debug, Default)]
+pub struct MxpState {
+    default_mode: LineMode,
+    line_mode: Option<LineMode>,
+    temp_secure: bool,
+    open_tags: Vec<OpenTag>,
+}
+
+impl MxpState {
+    fn current_mode(&self) -> LineMode {
+        if self.temp_secure {
+            LineMode::Secure
+        } else {
+            self.line_mode.unwrap_or(self.default_mode)
+        }
+    }
+
+    fn set_line_mode(&mut self, mode: u32, output: &mut String) {
+        match mode {
+            0 => self.line_mode = Some(LineMode::Open),
+            1 => self.line_mode = Some(LineMode::Secure),
+            2 => self.line_mode = Some(LineMode::Locked),
+            3 => {
+                self.close_all(output);
+                self.default_mode = LineMode::Open;
+                self.line_mode = None;
+            }
+            4 => self.temp_secure = true,
+            5 => self.default_mode = LineMode::Open,
+            6 => self.default_mode = LineMode::Secure,
+            7 => self.default_mode = LineMode::Locked,
+            _ => {} // Custom line tags are not supported
+        }
+    }
+
+    /// Process a complete line of text, converting MXP tags into ANSI
+    pub fn process_line(&mut self, line: &str) -> String {
+        self.process(line, true)
+    }
+
+    /// Process a partial line of text without affecting our state, since it will be
+    /// processed again once it's complete. Any incomplete tag at the end of the line
+    /// will be held back.
+    pub fn process_partial(&self, line: &str) -> String {
+        self.clone().process(line, false)
+    }
+
+    fn process(&mut self, line: &str, is_full_line: bool) -> String {
+        let body = line.trim_end_matches(['\r', '\n']);
+        let line_ending = &line[body.len()..];
+
+        let mut output = String::with_capacity(line.len());
+        let mut rest = body;
+        while let Some(ch) = rest.chars().next() {
+            if let Some((mode, len)) = parse_line_mode(rest) {
+                self.set_line_mode(mode, &mut output);
+                rest = &rest[len..];
+                continue;
+            }
+
+            let mode = self.current_mode();
+            if mode != LineMode::Locked {
+                if ch == '<' {
+                    if let Some(end) = find_tag_end(rest) {
+                        self.temp_secure = false;
+                        if let Some(tag) = Tag::parse(&rest[1..end]) {
+                            self.apply_tag(tag, mode, &mut output);
+                        }
+                        rest = &rest[end + 1..];
+                        continue;
+                    } else if !is_full_line {
+                        // Wait for the rest of the tag
+                        break;
+                    }
+                } else if ch == '&' {
+                    let decoded = rest[1..]
+                        .find(';')
+                        .filter(|end| *end <= 8)
+                        .and_then(|end| Some((decode_entity(&rest[1..end + 1])?, end + 2)));
+                    if let Some((decoded, len)) = decoded {
+                        self.push_text(decoded, &mut output);
+                        rest = &rest[len..];
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(len) = csi_len(rest) {
+                // Pass through any existing ANSI, without treating it as link text
+                output.push_str(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+
+            self.push_text(ch, &mut output);
+            rest = &rest[ch.len_utf8()..];
+        }
+
+        // Tags don't (currently) span lines
+        self.close_all(&mut output);
+        if is_full_line {
+            self.line_mode = None;
+            self.temp_secure = false;
+        }
+
+        output.push_str(line_ending);
+        output
+    }
+
+    fn push_text(&mut self, ch: char, output: &mut String) {
+        output.push(ch);
+        for tag in self.open_tags.iter_mut() {
+            if let OpenTag::Link { content, .. } = tag {
+                content.push(ch);
+            }
+        }
+    }
+
+    fn apply_tag(&mut self, tag: Tag, mode: LineMode, output: &mut String) {
+        if tag.closing {
+            self.close_tag(&tag.name, output);
+            return;
+        }
+
+        let opened = match tag.name.as_str() {
+            "b" | "bold" | "strong" => Some(OpenTag::Format {
+                name: "b",
+                reset: "\x1b[22m",
+            }),
+            "h" | "high" => Some(OpenTag::Format {
+                name: "h",
+                reset: "\x1b[22m",
+            }),
+            "i" | "italic" | "em" => Some(OpenTag::Format {
+                name: "i",
+                reset: "\x1b[23m",
+            }),
+            "u" | "underline" => Some(OpenTag::Format {
+                name: "u",
+                reset: "\x1b[24m",
+            }),
+            "s" | "strikeout" => Some(OpenTag::Format {
+                name: "s",
+                reset: "\x1b[29m",
+            }),
+            "c" | "color" => color_sgr(tag.param("fore", 0), tag.param("back", 1))
+                .map(|sgr| OpenTag::Color { name: "c", sgr }),
+            "font" => color_sgr(
+                tag.attrs.get("color").map(|s| s.as_str()),
+                tag.attrs.get("back").map(|s| s.as_str()),
+            )
+            .map(|sgr| OpenTag::Color { name: "font", sgr }),
+
+            // Secure tags:
+            "send" | "a" if mode == LineMode::Secure => {
+                let is_send = tag.name == "send";
+                Some(OpenTag::Link {
+                    name: if is_send { "send" } else { "a" },
+                    start: output.len(),
+                    href: tag.param("href", 0).map(|s| s.to_string()),
+                    hint: tag.param("hint", 1).map(|s| s.to_string()),
+                    prompt: is_send && tag.has_flag("prompt"),
+                    is_send,
+                    content: String::new(),
+                })
+            }
+
+            _ => None,
+        };
+
+        let Some(opened) = opened else {
+            return;
+        };
+
+        match &opened {
+            OpenTag::Format { name, .. } => output.push_str(match *name {
+                "b" | "h" => "\x1b[1m",
+                "i" => "\x1b[3m",
+                "u" => "\x1b[4m",
+                _ => "\x1b[9m",
+            }),
+            OpenTag::Color { sgr, .. } => output.push_str(sgr),
+            OpenTag::Link { .. } => {}
+        }
+        self.open_tags.push(opened);
+    }
+
+    fn close_tag(&mut self, name: &str, output: &mut String) {
+        let canonical = match name {
+            "bold" | "strong" => "b",
+            "high" => "h",
+            "italic" | "em" => "i",
+            "underline" => "u",
+            "strikeout" => "s",
+            "color" => "c",
+            other => other,
+        };
+
+        if let Some(index) = self
+            .open_tags
+            .iter()
+            .rposition(|tag| tag.name() == canonical)
+        {
+            while self.open_tags.len() > index {
+                self.close_last(output);
+            }
+        }
+    }
+
+    fn close_all(&mut self, output: &mut String) {
+        while !self.open_tags.is_empty() {
+            self.close_last(output);
+        }
+    }
+
+    fn close_last(&mut self, output: &mut String) {
+        let Some(tag) = self.open_tags.pop() else {
+            return;
+        };
+
+        match tag {
+            OpenTag::Format { reset, .. } => output.push_str(reset),
+            OpenTag::Color { .. } => {
+                output.push_str("\x1b[39;49m");
+
+                // Restore any outer color
+                let outer = self.open_tags.iter().rev().find_map(|tag| match tag {
+                    OpenTag::Color { sgr, .. } => Some(sgr),
+                    _ => None,
+                });
+                if let Some(sgr) = outer {
+                    output.push_str(sgr);
+                }
+            }
+            OpenTag::Link {
+                start,
+                href,
+                hint,
+                prompt,
+                is_send,
+                content,
+                ..
+            } => {
+                let href = match href {
+                    Some(href) => href.replace("&text;", &content),
+                    None => content,
+                };
+
+                let (params, uri) = if is_send {
+                    let mut params = Vec::new();
+                    if let Some(hint) = hint {
+                        params.push(format!("hint={}", percent_encode(&hint)));
+                    }
+                    if prompt {
+                        params.push("prompt=1".to_string());
+                    }
+                    (
+                        params.join(":"),
+                        format!("{SEND_SCHEME}{}", percent_encode(&href)),
+                    )
+                } else {
+                    (String::new(), href)
+                };
+
+                output.insert_str(
+                    start,
+                    &format!("{OSC_HYPERLINK}{params};{uri}{STRING_TERMINATOR}"),
+                );
+                output.push_str(OSC_HYPERLINK);
+                output.push(';');
+                output.push_str(STRING_TERMINATOR);
+            }
+        }
+    }
+}
+
+/// Text with any OSC 8 hyperlinks extracted into [TextLink]s
+pub struct ExtractedLinks {
+    pub ansi: String,
+    pub plain: String,
+    pub links: Vec<TextLink>,
+}
+
+fn parse_link_action(params: &str, uri: &str) -> TextLinkAction {
+    let Some(encoded) = uri.strip_prefix(SEND_SCHEME) else {
+        return TextLinkAction::Url {
+            href: uri.to_string(),
+        };
+    };
+
+    let mut hint = None;
+    let mut prompt = false;
+    for param in params.split(':') {
+        match param.split_once('=') {
+            Some(("hint", value)) => hint = Some(percent_decode(value)),
+            Some(("prompt", value)) => prompt = value == "1",
+            _ => {}
+        }
+    }
+
+    TextLinkAction::Send {
+        commands: percent_decode(encoded)
+            .split('|')
+            .map(|command| command.to_string())
+            .collect(),
+        hint,
+        prompt,
+    }
+}
+
+/// Find the end of the OSC sequence whose payload starts `text`, which may be
+/// terminated by either ST or BEL. Returns the length of the payload and of
+/// its terminator.
+fn find_osc_end(text: &str) -> Option<(usize, usize)> {
+    let bel = text.find('\x07').map(|end| (end, 1));
+    let st = text
+        .find(STRING_TERMINATOR)
+        .map(|end| (end, STRING_TERMINATOR.len()));
+    match (bel, st) {
+        (Some(bel), Some(st)) => Some(bel.min(st)),
+        (bel, st) => bel.or(st),
+    }
+}
+
+/// Extract any OSC 8 hyperlinks from the given ANSI text. Link ranges are byte
+/// offsets into the returned `plain` text, which is the `ansi` text as stripped
+/// by [Ansi::strip_ansi]. Unterminated OSC 8 sequences are left in `ansi`.
+pub fn extract_links(text: &str) -> ExtractedLinks {
+    let mut ansi = String::with_capacity(text.len());
+
+    // NOTE: Ranges here are byte offsets into `ansi`; they're converted below
+    let mut ansi_links = Vec::new();
+    let mut open_link: Option<(usize, TextLinkAction)> = None;
+
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(link) = rest.strip_prefix(OSC_HYPERLINK) {
+            if let Some((end, terminator_len)) = find_osc_end(link) {
+                let (params, uri) = link[..end].split_once(';').unwrap_or(("", &link[..end]));
+                if let Some((start, action)) = open_link.take() {
+                    ansi_links.push((start, ansi.len(), action));
+                }
+                if !uri.is_empty() {
+                    open_link = Some((ansi.len(), parse_link_action(params, uri)));
+                }
+
+                rest = &link[end + terminator_len..];
+                continue;
+            }
+        }
+
+        // Copy everything else (including any other escape sequences) verbatim
+        let next = rest[1..]
+            .find(OSC_HYPERLINK)
+            .map(|index| index + 1)
+            .unwrap_or(rest.len());
+        ansi.push_str(&rest[..next]);
+        rest = &rest[next..];
+    }
+
+    if let Some((start, action)) = open_link {
+        ansi_links.push((start, ansi.len(), action));
+    }
+
+    let plain_offset = |ansi_offset: usize| {
+        Ansi::from(ansi[..ansi_offset].to_string())
+            .strip_ansi()
+            .len()
+    };
+    let links = ansi_links
+        .into_iter()
+        .map(|(start, end, action)| TextLink {
+            start: plain_offset(start),
+            end: plain_offset(end),
+            action,
+        })
+        .collect();
+    let plain = Ansi::from(ansi.clone()).strip_ansi().to_string();
+
+    ExtractedLinks { ansi, plain, links }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process_secure(input: &str) -> String {
+        let mut state = MxpState {
+            default_mode: LineMode::Secure,
+            ..Default::default()
+        };
+        state.process_line(input)
+    }
+
+    #[test]
+    fn format_tags_test() {
+        let mut state = MxpState::default();
+        let output = state.process_line("<b>Bold</b> and <i>italic</I>\r\n");
+        assert_eq!(output, "\x1b[1mBold\x1b[22m and \x1b[3mitalic\x1b[23m\r\n");
+    }
+
+    #[test]
+    fn color_tags_test() {
+        let mut state = MxpState::default();
+        let output = state.process_line("<color red>Red <c fore=#0000ff>Blue</c> Red</color>");
+        assert_eq!(
+            output,
+            "\x1b[38;2;255;0;0mRed \x1b[38;2;0;0;255mBlue\x1b[39;49m\x1b[38;2;255;0;0m Red\x1b[39;49m"
+        );
+    }
+
+    #[test]
+    fn entities_test() {
+        let mut state = MxpState::default();
+        let output = state.process_line("1 &lt; 2 &amp;&amp; R&D&#33;");
+        assert_eq!(output, "1 < 2 && R&D!");
+    }
+
+    #[test]
+    fn send_link_test() {
+        let output = process_secure("Exits: <send>north</send>\r\n");
+        assert_eq!(
+            output,
+            "Exits: \x1b]8;;send:north\x1b\\north\x1b]8;;\x1b\\\r\n"
+        );
+
+        let extracted = extract_links(&output);
+        assert_eq!(extracted.ansi, "Exits: north\r\n");
+        assert_eq!(extracted.plain, "Exits: north\r\n");
+        assert_eq!(
+            extracted.links,
+            vec![TextLink {
+                start: 7,
+                end: 12,
+                action: TextLinkAction::Send {
+                    commands: vec!["north".to_string()],
+                    hint: None,
+                    prompt: false,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn send_menu_link_test() {
+        let output = process_secure(
+            r#"<send href="get &text;|drop &text;" hint="Sword|Get|Drop" prompt>sword</send>"#,
+        );
+        let extracted = extract_links(&output);
+        assert_eq!(extracted.plain, "sword");
+        assert_eq!(
+            extracted.links,
+            vec![TextLink {
+                start: 0,
+                end: 5,
+                action: TextLinkAction::Send {
+                    commands: vec!["get sword".to_string(), "drop sword".to_string()],
+                    hint: Some("Sword|Get|Drop".to_string()),
+                    prompt: true,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn url_link_test() {
+        let output = process_secure(r#"See <a href="https://eternia.game">the site</a>"#);
+        let extracted = extract_links(&output);
+        assert_eq!(extracted.plain, "See the site");
+        assert_eq!(
+            extracted.links,
+            vec![TextLink {
+                start: 4,
+                end: 12,
+                action: TextLinkAction::Url {
+                    href: "https://eternia.game".to_string()
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn bel_terminated_link_test() {
+        let extracted = extract_links("Go \x1b]8;;send:north\x07\x1b[1mnorth\x1b[0m\x1b]8;;\x07!");
+        assert_eq!(extracted.ansi, "Go \x1b[1mnorth\x1b[0m!");
+        assert_eq!(extracted.plain, "Go north!");
+        assert_eq!(extracted.links.len(), 1);
+        assert_eq!(extracted.links[0].start, 3);
+        assert_eq!(extracted.links[0].end, 8);
+    }
+
+    #[test]
+    fn unterminated_link_test() {
+        let extracted = extract_links("\x1b]2;Title\x07Exits: \x1b]8;;send:north");
+        assert_eq!(extracted.ansi, "\x1b]2;Title\x07Exits: \x1b]8;;send:north");
+        assert!(extracted.links.is_empty());
+        assert_eq!(extracted.plain, "Exits: ");
+    }
+
+    #[test]
+    fn secure_tags_in_open_mode_test() {
+        let mut state = MxpState::default();
+        let output = state.process_line("<send>north</send>");
+        assert_eq!(output, "north");
+    }
+
+    #[test]
+    fn line_modes_test() {
+        let mut state = MxpState::default();
+        assert_eq!(
+            state.process_line("\x1b[1z<send>north</send>\r\n"),
+            "\x1b]8;;send:north\x1b\\north\x1b]8;;\x1b\\\r\n"
+        );
+
+        // Secure line mode only lasts for a single line
+        assert_eq!(state.process_line("<send>north</send>\r\n"), "north\r\n");
+
+        assert_eq!(
+            state.process_line("\x1b[2z<b>Not bold</b>\r\n"),
+            "<b>Not bold</b>\r\n"
+        );
+
+        state.process_line("\x1b[7z");
+        assert_eq!(state.process_line("<b>Locked</b>"), "<b>Locked</b>");
+    }
+
+    #[test]
+    fn partial_tag_test() {
+        let mut state = MxpState::default();
+        assert_eq!(state.process_partial("Hello <b"), "Hello ");
+        assert_eq!(
+            state.process_partial("Hello <b>there"),
+            "Hello \x1b[1mthere\x1b[22m"
+        );
+        assert_eq!(state.process_line("a < b"), "a < b");
+    }
+}
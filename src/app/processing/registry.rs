@@ -0,0 +1,355 @@
+use std::{
+    collections::HashSet,
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+use crate::app::{matchers::MatcherOptions, Id};
+
+/// Anything that may be stored in a [MatcherRegistry]
+pub trait RegistryValue {
+    fn options(&self) -> &MatcherOptions;
+}
+
+pub struct Entry<T> {
+    id: Id,
+    group: Option<String>,
+
+    /// Cached from `disabled_groups`, so skipping disabled entries is cheap
+    enabled: bool,
+
+    /// How many more times this entry may fire, if limited by `max_fires`
+    fires_remaining: Option<u32>,
+    expires_at: Option<Instant>,
+
+    value: T,
+}
+
+impl<T> Entry<T> {
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    /// To be called whenever this entry's matcher fires. Once it has fired
+    /// `max_fires` times it is considered expired, and will be skipped until it
+    /// can be removed by [MatcherRegistry::remove_expired].
+    pub fn on_fired(&mut self) {
+        if let Some(remaining) = self.fires_remaining.as_mut() {
+            *remaining = remaining.saturating_sub(1);
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.fires_remaining == Some(0) || self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+impl<T> Deref for Entry<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Entry<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+/// An ordered collection of registered matchers (or anything else), each of
+/// which is assigned an id that stays stable until it is removed. Entries are
+/// kept sorted by descending priority, and in registration order within the
+/// same priority. Entries may belong to a named group, and whole groups may be
+/// disabled at once. Entries with `max_fires` or `expires_after_ms` expire
+/// automatically, and are skipped until they're removed.
+pub struct MatcherRegistry<T> {
+    next_id: Id,
+    entries: Vec<Entry<T>>,
+    disabled_groups: HashSet<String>,
+}
+
+impl<T> Default for MatcherRegistry<T> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            entries: Vec::new(),
+            disabled_groups: HashSet::new(),
+        }
+    }
+}
+
+impl<T: RegistryValue> MatcherRegistry<T> {
+    /// If `replacing` is the id of an existing entry, replace it in place,
+    /// keeping its id and (if its priority is unchanged) its position.
+    /// Otherwise, insert `entry` with a new id after any others with the same
+    /// priority. Either way, returns the id of `entry`. Any limits on how
+    /// long the entry lives start over from now.
+    pub fn upsert(&mut self, replacing: Option<Id>, group: Option<String>, value: T) -> Id {
+        let enabled = !group
+            .as_ref()
+            .is_some_and(|group| self.disabled_groups.contains(group));
+        let options = value.options();
+        let priority = options.priority;
+        let fires_remaining = options.max_fires;
+        let expires_at = options
+            .expires_after_ms
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+
+        let id = if let Some(existing) = replacing.and_then(|id| self.position(id)) {
+            if self.entries[existing].options().priority == priority {
+                let entry = &mut self.entries[existing];
+                entry.group = group;
+                entry.enabled = enabled;
+                entry.fires_remaining = fires_remaining;
+                entry.expires_at = expires_at;
+                entry.value = value;
+                return entry.id;
+            }
+
+            self.entries.remove(existing).id
+        } else {
+            // NOTE: Ids are never reused, even across clear(), so a stale id held
+            // by a client can't accidentally refer to some newer entry
+            let id = self.next_id;
+            self.next_id += 1;
+            id
+        };
+
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.options().priority < priority)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(
+            index,
+            Entry {
+                id,
+                group,
+                enabled,
+                fires_remaining,
+                expires_at,
+                value,
+            },
+        );
+        id
+    }
+}
+
+impl<T> MatcherRegistry<T> {
+    pub fn get(&self, id: Id) -> Option<&Entry<T>> {
+        self.position(id).map(|index| &self.entries[index])
+    }
+
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut Entry<T>> {
+        self.position(id).map(|index| &mut self.entries[index])
+    }
+
+    /// Remove the entry with the given id, if it exists *and* satisfies
+    /// `predicate`
+    pub fn remove_if<P: FnOnce(&T) -> bool>(&mut self, id: Id, predicate: P) -> Option<T> {
+        let index = self.position(id)?;
+        if predicate(&self.entries[index].value) {
+            Some(self.entries.remove(index).value)
+        } else {
+            None
+        }
+    }
+
+    /// Remove every entry that has fired `max_fires` times or outlived
+    /// `expires_after_ms`, returning them along with their ids
+    pub fn remove_expired(&mut self) -> Vec<(Id, T)> {
+        let now = Instant::now();
+        if !self.entries.iter().any(|entry| entry.is_expired(now)) {
+            return Vec::new();
+        }
+
+        let mut expired = Vec::new();
+        for entry in std::mem::take(&mut self.entries) {
+            if entry.is_expired(now) {
+                expired.push((entry.id, entry.value));
+            } else {
+                self.entries.push(entry);
+            }
+        }
+        expired
+    }
+
+    /// Enable or disable every entry in `group`, including any registered to it
+    /// later
+    pub fn set_group_enabled(&mut self, group: &str, enabled: bool) {
+        if enabled {
+            self.disabled_groups.remove(group);
+        } else {
+            self.disabled_groups.insert(group.to_string());
+        }
+
+        for entry in &mut self.entries {
+            if entry.group.as_deref() == Some(group) {
+                entry.enabled = enabled;
+            }
+        }
+    }
+
+    /// Iterate mutably over enabled, unexpired entries
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Entry<T>> {
+        let now = Instant::now();
+        self.entries
+            .iter_mut()
+            .filter(move |entry| entry.enabled && !entry.is_expired(now))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.disabled_groups.clear();
+    }
+
+    fn position(&self, id: Id) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str, MatcherOptions);
+
+    impl RegistryValue for Named {
+        fn options(&self) -> &MatcherOptions {
+            &self.1
+        }
+    }
+
+    fn named(name: &'static str, priority: i32) -> Named {
+        Named(
+            name,
+            MatcherOptions {
+                priority,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn names(registry: &mut MatcherRegistry<Named>) -> Vec<&'static str> {
+        registry.iter_mut().map(|entry| entry.0).collect()
+    }
+
+    #[test]
+    fn upsert_keeps_position_test() {
+        let mut registry = MatcherRegistry::default();
+        let first = registry.upsert(None, None, named("first", 0));
+        let second = registry.upsert(None, None, named("second", 0));
+        assert_ne!(first, second);
+
+        assert_eq!(
+            registry.upsert(Some(first), None, named("replaced", 0)),
+            first
+        );
+        assert_eq!(names(&mut registry), vec!["replaced", "second"]);
+
+        // Unknown ids are simply appended:
+        let third = registry.upsert(Some(42), None, named("third", 0));
+        assert_ne!(third, 42);
+        assert_eq!(registry.get(third).map(|entry| entry.0), Some("third"));
+    }
+
+    #[test]
+    fn ids_are_not_reused_test() {
+        let mut registry = MatcherRegistry::default();
+        let first = registry.upsert(None, None, named("first", 0));
+        assert!(registry.remove_if(first, |_| false).is_none());
+        assert_eq!(
+            registry.remove_if(first, |_| true).map(|value| value.0),
+            Some("first")
+        );
+        assert!(registry.remove_if(first, |_| true).is_none());
+
+        registry.clear();
+        assert_ne!(registry.upsert(None, None, named("second", 0)), first);
+    }
+
+    #[test]
+    fn disabled_groups_test() {
+        let mut registry = MatcherRegistry::default();
+        registry.upsert(None, Some("combat".to_string()), named("kill", 0));
+        registry.upsert(None, None, named("always", 0));
+
+        registry.set_group_enabled("combat", false);
+        registry.upsert(None, Some("combat".to_string()), named("flee", 0));
+        assert_eq!(names(&mut registry), vec!["always"]);
+
+        registry.set_group_enabled("combat", true);
+        assert_eq!(names(&mut registry), vec!["kill", "always", "flee"]);
+    }
+
+    #[test]
+    fn priority_order_test() {
+        let mut registry = MatcherRegistry::default();
+        let low = registry.upsert(None, None, named("low", -1));
+        registry.upsert(None, None, named("first", 0));
+        registry.upsert(None, None, named("high", 10));
+        registry.upsert(None, None, named("second", 0));
+        assert_eq!(names(&mut registry), vec!["high", "first", "second", "low"]);
+
+        // Changing the priority of an entry moves it, but keeps its id:
+        assert_eq!(registry.upsert(Some(low), None, named("promoted", 5)), low);
+        assert_eq!(
+            names(&mut registry),
+            vec!["high", "promoted", "first", "second"]
+        );
+    }
+
+    #[test]
+    fn max_fires_test() {
+        let mut registry = MatcherRegistry::default();
+        let once = registry.upsert(
+            None,
+            None,
+            Named(
+                "once",
+                MatcherOptions {
+                    max_fires: Some(1),
+                    ..Default::default()
+                },
+            ),
+        );
+        registry.upsert(None, None, named("always", 0));
+        assert!(registry.remove_expired().is_empty());
+
+        for entry in registry.iter_mut() {
+            entry.on_fired();
+        }
+        assert_eq!(names(&mut registry), vec!["always"]);
+
+        let expired = registry.remove_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].0, once);
+        assert!(registry.get(once).is_none());
+        assert!(registry.remove_expired().is_empty());
+    }
+
+    #[test]
+    fn expires_after_test() {
+        let mut registry = MatcherRegistry::default();
+        let expiring = registry.upsert(
+            None,
+            None,
+            Named(
+                "expiring",
+                MatcherOptions {
+                    expires_after_ms: Some(0),
+                    ..Default::default()
+                },
+            ),
+        );
+        registry.upsert(None, None, named("always", 0));
+        assert_eq!(names(&mut registry), vec!["always"]);
+
+        let expired = registry.remove_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].0, expiring);
+        assert_eq!(names(&mut registry), vec!["always"]);
+    }
+}
@@ -1,11 +1,18 @@
 use std::{future::Future, io, pin::Pin};
 
 use crate::{
-    app::matchers::{MatchResult, MatchedResult, Matcher},
-    daemon::notifications::MatchContext,
+    app::{
+        clearable::Clearable,
+        matchers::{MatchResult, MatchedResult, Matcher, MatcherOptions},
+        Id,
+    },
+    daemon::notifications::{DaemonNotification, MatchContext, MatcherKind},
 };
 
-use super::ansi::Ansi;
+use super::{
+    ansi::Ansi,
+    registry::{MatcherRegistry, RegistryValue},
+};
 
 const MAX_RECURSION: usize = 100;
 
@@ -24,13 +31,27 @@ struct RegisteredMatcher {
     on_match: Box<MatchHandler>,
 }
 
+impl RegistryValue for RegisteredMatcher {
+    fn options(&self) -> &MatcherOptions {
+        &self.matcher.options
+    }
+}
+
 #[derive(Default)]
 pub struct SendTextProcessor {
-    matchers: Vec<RegisteredMatcher>,
+    matchers: MatcherRegistry<RegisteredMatcher>,
 }
 
 impl SendTextProcessor {
-    pub fn register_matcher<R, F>(&mut self, matcher: Matcher, on_match: R)
+    /// Register a matcher, returning its id. If `replacing` is the id of an
+    /// existing matcher, that matcher is replaced in place instead.
+    pub fn register_matcher<R, F>(
+        &mut self,
+        replacing: Option<Id>,
+        group: Option<String>,
+        matcher: Matcher,
+        on_match: R,
+    ) -> Id
     where
         R: 'static + (Fn(MatchContext) -> F) + Send + Sync,
         F: 'static + Future<Output = io::Result<ProcessResult>> + Send + Sync,
@@ -39,13 +60,41 @@ impl SendTextProcessor {
             panic!("Matcher ({matcher:?}) is unexpectedly `consume`");
         }
 
-        self.matchers.push(RegisteredMatcher {
-            matcher,
-            on_match: Box::new(move |context| Box::pin(on_match(context))),
-        })
+        self.matchers.upsert(
+            replacing,
+            group,
+            RegisteredMatcher {
+                matcher,
+                on_match: Box::new(move |context| Box::pin(on_match(context))),
+            },
+        )
+    }
+
+    /// Returns true if a matcher with the given id was removed
+    pub fn unregister_matcher(&mut self, id: Id) -> bool {
+        self.matchers.remove_if(id, |_| true).is_some()
+    }
+
+    pub fn set_matcher_group_enabled(&mut self, group: &str, enabled: bool) {
+        self.matchers.set_group_enabled(group, enabled);
     }
 
-    pub async fn process(&self, input: String) -> io::Result<Option<String>> {
+    /// Remove any matchers that have expired, returning a notification for each
+    pub fn remove_expired_matchers(&mut self) -> Vec<DaemonNotification> {
+        self.matchers
+            .remove_expired()
+            .into_iter()
+            .map(|(matcher_id, _)| DaemonNotification::MatcherExpired {
+                kind: MatcherKind::Alias,
+                matcher_id,
+            })
+            .collect()
+    }
+
+    /// Process `input`, returning the replaced text or `None` if processing was
+    /// stopped. Matchers that expire as a result are skipped, but not removed
+    /// until [Self::remove_expired_matchers] is called.
+    pub async fn process(&mut self, input: String) -> io::Result<Option<String>> {
         let mut result = input;
 
         for _ in 0..MAX_RECURSION {
@@ -69,12 +118,12 @@ impl SendTextProcessor {
         ))
     }
 
-    async fn process_once(&self, input: String) -> io::Result<ProcessResult> {
+    async fn process_once(&mut self, input: String) -> io::Result<ProcessResult> {
         // TODO The conversion from String <-> Ansi could be cheaper... Or perhaps
         // we could refactor to use a trait instead of Ansi?
         let mut to_match: Ansi = input.clone().into();
         let mut unchanged = true;
-        for matcher in &self.matchers {
+        for matcher in self.matchers.iter_mut() {
             match matcher.matcher.try_match(to_match) {
                 MatchResult::Ignored(ignored) => to_match = ignored,
                 MatchResult::Matched(MatchedResult {
@@ -84,12 +133,20 @@ impl SendTextProcessor {
                 }) => {
                     // NOTE: Since the matcher *shouldn't* consume, remaining *should*
                     // be the original input
-                    if let Some(replaced) = self
-                        .process_match(matcher, context, remaining.strip_ansi().to_string())
-                        .await?
+                    matcher.on_fired();
+                    if let Some(replaced) =
+                        Self::process_match(matcher, context, remaining.strip_ansi().to_string())
+                            .await?
                     {
                         unchanged = false;
                         to_match = replaced.into();
+
+                        // NOTE: Lower-priority matchers will still get a chance at
+                        // the replacement when it gets processed again, but not
+                        // in this pass (see MatcherOptions::fallthrough)
+                        if !matcher.matcher.options.fallthrough {
+                            break;
+                        }
                     } else {
                         return Ok(ProcessResult::Stop);
                     }
@@ -112,7 +169,6 @@ impl SendTextProcessor {
     }
 
     async fn process_match(
-        &self,
         matcher: &RegisteredMatcher,
         context: MatchContext,
         mut original: String,
@@ -129,9 +185,15 @@ impl SendTextProcessor {
     }
 }
 
+impl Clearable for SendTextProcessor {
+    fn clear(&mut self) {
+        self.matchers.clear();
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::app::matchers::MatcherSpec;
+    use crate::app::matchers::{MatcherOptions, MatcherSpec};
 
     use super::*;
 
@@ -139,6 +201,8 @@ mod tests {
     async fn single_replacement_test() -> io::Result<()> {
         let mut processor = SendTextProcessor::default();
         processor.register_matcher(
+            None,
+            None,
             MatcherSpec::Regex {
                 options: Default::default(),
                 source: "activate (.*)".to_string(),
@@ -166,6 +230,8 @@ mod tests {
     async fn non_start_replacement_test() -> io::Result<()> {
         let mut processor = SendTextProcessor::default();
         processor.register_matcher(
+            None,
+            None,
             MatcherSpec::Regex {
                 options: Default::default(),
                 source: "honor (.*)".to_string(),
@@ -195,6 +261,8 @@ mod tests {
     async fn detect_recursion_test() -> io::Result<()> {
         let mut processor = SendTextProcessor::default();
         processor.register_matcher(
+            None,
+            None,
             MatcherSpec::Regex {
                 options: Default::default(),
                 source: "honor (.*)".to_string(),
@@ -220,6 +288,8 @@ mod tests {
     async fn multi_replacement_test() -> io::Result<()> {
         let mut processor = SendTextProcessor::default();
         processor.register_matcher(
+            None,
+            None,
             MatcherSpec::Regex {
                 options: Default::default(),
                 source: "^honor (.*)".to_string(),
@@ -235,6 +305,8 @@ mod tests {
         );
 
         processor.register_matcher(
+            None,
+            None,
             MatcherSpec::Regex {
                 options: Default::default(),
                 source: "honor of ([a-z]+)".to_string(),
@@ -257,4 +329,95 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn priority_test() -> io::Result<()> {
+        let mut processor = SendTextProcessor::default();
+        for (priority, replacement) in [(0, "kick"), (10, "kill")] {
+            processor.register_matcher(
+                None,
+                None,
+                MatcherSpec::Regex {
+                    options: MatcherOptions {
+                        priority,
+                        ..Default::default()
+                    },
+                    source: "^k$".to_string(),
+                }
+                .try_into()
+                .unwrap(),
+                move |_| async move { Ok(ProcessResult::ReplaceWith(replacement.to_string())) },
+            );
+        }
+
+        let result = processor.process("k".to_string()).await?;
+        assert_eq!(result.expect("Processing was unexpectedly stopped"), "kill");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lower_priority_expands_replacement_test() -> io::Result<()> {
+        let mut processor = SendTextProcessor::default();
+        for (priority, source, replacement) in [(10, "^k$", "kk"), (0, "^kk$", "kick")] {
+            processor.register_matcher(
+                None,
+                None,
+                MatcherSpec::Regex {
+                    options: MatcherOptions {
+                        priority,
+                        ..Default::default()
+                    },
+                    source: source.to_string(),
+                }
+                .try_into()
+                .unwrap(),
+                move |_| async move { Ok(ProcessResult::ReplaceWith(replacement.to_string())) },
+            );
+        }
+
+        // The higher-priority alias stops the first pass, but its replacement
+        // is processed again, where the lower-priority alias matches:
+        let result = processor.process("k".to_string()).await?;
+        assert_eq!(result.expect("Processing was unexpectedly stopped"), "kick");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn max_fires_test() -> io::Result<()> {
+        let mut processor = SendTextProcessor::default();
+        let id = processor.register_matcher(
+            None,
+            None,
+            MatcherSpec::Regex {
+                options: MatcherOptions {
+                    max_fires: Some(1),
+                    ..Default::default()
+                },
+                source: "^k$".to_string(),
+            }
+            .try_into()
+            .unwrap(),
+            |_| async move { Ok(ProcessResult::ReplaceWith("kick".to_string())) },
+        );
+
+        let result = processor.process("k".to_string()).await?;
+        assert_eq!(result.expect("Processing was unexpectedly stopped"), "kick");
+
+        let result = processor.process("k".to_string()).await?;
+        assert_eq!(result.expect("Processing was unexpectedly stopped"), "k");
+
+        let expired = processor.remove_expired_matchers();
+        assert!(matches!(
+            expired[..],
+            [DaemonNotification::MatcherExpired {
+                kind: MatcherKind::Alias,
+                matcher_id,
+            }] if matcher_id == id
+        ));
+        assert!(processor.remove_expired_matchers().is_empty());
+
+        Ok(())
+    }
 }
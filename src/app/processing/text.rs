@@ -8,47 +8,79 @@ use bytes::{Buf, Bytes};
 use crate::{
     app::{
         clearable::Clearable,
-        matchers::{MatchResult, MatchedResult, Matcher},
+        matchers::{multi_line::BlockStep, MatchResult, MatchedResult, Matcher, MatcherOptions},
         Id,
     },
     cli::ui::UiState,
     daemon::{
         channel::RespondedChannel,
-        notifications::{DaemonNotification, MatchContext},
+        notifications::{DaemonNotification, MatchContext, MatcherKind},
     },
 };
 
-use super::ansi::{Ansi, AnsiMut};
+use super::{
+    ansi::{Ansi, AnsiMut},
+    mxp::MxpState,
+    registry::{MatcherRegistry, RegistryValue},
+};
 
 const NEWLINE_BYTE: u8 = b'\n';
 
 type MatchHandler = dyn FnMut(MatchContext) -> io::Result<()> + Send;
 type LineHandler = dyn Fn(&mut Ansi) -> io::Result<()> + Send;
 
+/// What registered a matcher
 #[derive(Debug, PartialEq)]
-pub enum MatcherId {
-    Handler(Id),
+pub enum MatcherSource {
+    Trigger { handler_id: Id },
     Prompt { group: Id, index: usize },
 }
 
+impl MatcherSource {
+    pub fn is_prompt(&self) -> bool {
+        matches!(self, MatcherSource::Prompt { .. })
+    }
+}
+
 struct RegisteredMatcher {
-    #[allow(dead_code)]
-    id: MatcherId,
+    source: MatcherSource,
     matcher: Matcher,
     mode: MatcherMode,
     on_match: Box<MatchHandler>,
 }
 
+impl RegistryValue for RegisteredMatcher {
+    fn options(&self) -> &MatcherOptions {
+        &self.matcher.options
+    }
+}
+
 struct RegisteredLineProcessor {
     process: Box<LineHandler>,
 }
 
+/// Consecutive lines that may be matched by a multi-line matcher
+struct PendingBlock {
+    matcher_id: Id,
+    lines: Vec<Ansi>,
+
+    /// The matchers that have already been tried against the first line (up to
+    /// and including the one that started this block), so they aren't tried
+    /// again if the block is abandoned
+    start_matched_by: Vec<Id>,
+
+    /// If set, `lines` have been withheld from the output
+    consume: bool,
+}
+
 #[derive(Default)]
 pub struct TextProcessor {
-    matchers: Vec<RegisteredMatcher>,
+    matchers: MatcherRegistry<RegisteredMatcher>,
     processors: Vec<RegisteredLineProcessor>,
     auto_prompt_processor: Option<RegisteredLineProcessor>,
     pending_line: AnsiMut,
+    pending_block: Option<PendingBlock>,
+    mxp: Option<MxpState>,
 }
 
 pub enum SystemMessage {
@@ -82,17 +114,12 @@ pub trait ProcessorOutputReceiver {
     fn notification(&mut self, notification: DaemonNotification) -> io::Result<()>;
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MatcherMode {
     PartialLine,
     FullLine,
 }
 
-enum PerformMatchResult<'a> {
-    Matched(&'a mut RegisteredMatcher, MatchedResult),
-    Ignored(Ansi),
-}
-
 pub trait ProcessorOutputReceiverFactory: Clone + Send {
     type Implementation: ProcessorOutputReceiver + Send;
 
@@ -128,21 +155,92 @@ impl TextProcessor {
             bytes.advance(read);
         }
 
+        self.notify_expired_matchers(receiver)
+    }
+
+    /// Remove any matchers that have expired, returning a notification for each
+    pub fn remove_expired_matchers(&mut self) -> Vec<DaemonNotification> {
+        self.matchers
+            .remove_expired()
+            .into_iter()
+            .map(
+                |(matcher_id, registered)| DaemonNotification::MatcherExpired {
+                    kind: if registered.source.is_prompt() {
+                        MatcherKind::Prompt
+                    } else {
+                        MatcherKind::Trigger
+                    },
+                    matcher_id,
+                },
+            )
+            .collect()
+    }
+
+    fn notify_expired_matchers<R: ProcessorOutputReceiver>(
+        &mut self,
+        receiver: &mut R,
+    ) -> io::Result<()> {
+        for notification in self.remove_expired_matchers() {
+            receiver.notification(notification)?;
+        }
         Ok(())
     }
 
+    /// Enable or disable MXP parsing; MXP state is reset either way
+    pub fn set_mxp_enabled(&mut self, enabled: bool) {
+        self.mxp = if enabled {
+            Some(MxpState::default())
+        } else {
+            None
+        };
+    }
+
+    fn apply_mxp(&mut self, line: Ansi, is_full_line: bool) -> Ansi {
+        match self.mxp.as_mut() {
+            Some(mxp) if is_full_line => Ansi::from(mxp.process_line(&line)),
+            Some(mxp) => Ansi::from(mxp.process_partial(&line)),
+
+            None => line,
+        }
+    }
+
     pub fn on_end_of_prompt<R: ProcessorOutputReceiver>(
         &mut self,
         receiver: &mut R,
     ) -> io::Result<()> {
         self.clean_trailing_cr();
+
+        // The server is waiting on us, so any pending block won't get any more lines
+        if let Some(block) = self.pending_block.take() {
+            let step = match self.matchers.get(block.matcher_id) {
+                Some(registered) => registered.matcher.interrupt_block(),
+                None => BlockStep::Abandon,
+            };
+            match step {
+                BlockStep::Abandon if block.consume => {
+                    receiver.clear_partial_line()?;
+                    self.release_block(block, receiver)?;
+
+                    // Restore the prompt we just cleared
+                    self.process_pending_line(false, receiver)?;
+                }
+                BlockStep::Abandon => {}
+                _ => self.complete_block(block)?,
+            }
+        }
+
         if let Some(processor) = self.auto_prompt_processor.as_ref() {
-            let mut prompt = self.pending_line.take();
+            let prompt = self.pending_line.take();
+            let mut prompt = match self.mxp.as_mut() {
+                Some(mxp) => Ansi::from(mxp.process_line(&prompt)),
+                None => prompt,
+            };
             (processor.process)(&mut prompt)?;
             receiver.clear_partial_line()?;
             receiver.finish_line()?;
         }
-        Ok(())
+
+        self.notify_expired_matchers(receiver)
     }
 
     fn clean_trailing_cr(&mut self) {
@@ -169,18 +267,24 @@ impl TextProcessor {
 
         receiver.clear_partial_line()?;
 
-        let (match_mode, to_match) = if has_full_line {
-            let mut full_line = self.pending_line.take();
+        let to_print = if has_full_line {
+            let full_line = self.pending_line.take();
+            let mut full_line = self.apply_mxp(full_line, true);
 
             self.perform_processing(&mut full_line)?;
 
-            (MatcherMode::FullLine, full_line)
+            if full_line.is_empty() {
+                // A processor consumed the whole line; there's nothing to print
+                return receiver.finish_line();
+            }
+
+            self.process_full_line(full_line, receiver)?
         } else {
-            (MatcherMode::PartialLine, self.pending_line.clone().take())
+            let partial_line = self.pending_line.clone().take();
+            let partial_line = self.apply_mxp(partial_line, false);
+            self.perform_and_handle_match(partial_line, MatcherMode::PartialLine, false)?
         };
 
-        let to_print = self.perform_and_handle_match(to_match, match_mode)?;
-
         if let Some(to_print) = to_print {
             receiver.text(to_print)?;
 
@@ -194,19 +298,50 @@ impl TextProcessor {
         Ok(())
     }
 
+    /// Register a matcher, returning its id. If `replacing` is the id of an
+    /// existing matcher from the same kind of [MatcherSource], that matcher is
+    /// replaced in place instead.
     pub fn register_matcher<R: 'static + FnMut(MatchContext) -> io::Result<()> + Send>(
         &mut self,
-        id: MatcherId,
+        replacing: Option<Id>,
+        group: Option<String>,
+        source: MatcherSource,
         matcher: Matcher,
         mode: MatcherMode,
         on_match: R,
-    ) {
-        self.matchers.push(RegisteredMatcher {
-            id,
-            matcher,
-            mode,
-            on_match: Box::new(on_match),
-        })
+    ) -> Id {
+        let replacing = replacing.filter(|id| {
+            self.matchers
+                .get(*id)
+                .is_some_and(|existing| existing.source.is_prompt() == source.is_prompt())
+        });
+
+        self.matchers.upsert(
+            replacing,
+            group,
+            RegisteredMatcher {
+                source,
+                matcher,
+                mode,
+                on_match: Box::new(on_match),
+            },
+        )
+    }
+
+    /// Unregister the matcher with the given id, if it exists and its
+    /// [MatcherSource] satisfies `predicate`. Returns true if it was removed.
+    pub fn unregister_matcher<P: FnOnce(&MatcherSource) -> bool>(
+        &mut self,
+        id: Id,
+        predicate: P,
+    ) -> bool {
+        self.matchers
+            .remove_if(id, |existing| predicate(&existing.source))
+            .is_some()
+    }
+
+    pub fn set_matcher_group_enabled(&mut self, group: &str, enabled: bool) {
+        self.matchers.set_group_enabled(group, enabled);
     }
 
     pub fn register_processor<P: 'static + Fn(&mut Ansi) -> io::Result<()> + Send>(
@@ -234,50 +369,165 @@ impl TextProcessor {
         Ok(())
     }
 
-    fn perform_match(&mut self, mut to_match: Ansi, mode: MatcherMode) -> PerformMatchResult {
-        for m in &mut self.matchers {
-            if mode < m.mode {
-                continue;
-            }
-            to_match = match m.matcher.try_match(to_match) {
-                MatchResult::Ignored(ansi) => ansi,
-                MatchResult::Matched(matched) => {
-                    return PerformMatchResult::Matched(m, matched);
+    fn process_full_line<R: ProcessorOutputReceiver>(
+        &mut self,
+        mut line: Ansi,
+        receiver: &mut R,
+    ) -> io::Result<Option<Ansi>> {
+        if let Some(mut block) = self.pending_block.take() {
+            let step = match self.matchers.get(block.matcher_id) {
+                Some(registered) => registered.matcher.continue_block(&block.lines, &mut line),
+                None => BlockStep::Abandon,
+            };
+
+            match step {
+                BlockStep::Continue => {
+                    block.lines.push(line.clone());
+                    let consume = block.consume;
+                    self.pending_block = Some(block);
+                    if consume {
+                        return Ok(None);
+                    }
+                    return self.perform_and_handle_match(line, MatcherMode::FullLine, false);
+                }
+
+                BlockStep::Complete {
+                    includes_line: true,
+                } => {
+                    block.lines.push(line.clone());
+                    let consume = block.consume;
+                    self.complete_block(block)?;
+                    if consume {
+                        return Ok(None);
+                    }
+                    return self.perform_and_handle_match(line, MatcherMode::FullLine, false);
+                }
+
+                BlockStep::Complete {
+                    includes_line: false,
+                } => self.complete_block(block)?,
+
+                BlockStep::Abandon => {
+                    if block.consume {
+                        self.release_block(block, receiver)?;
+                    }
                 }
             }
         }
 
-        PerformMatchResult::Ignored(to_match)
+        // NOTE: The line was not part of any block, so it may start a new one
+        self.perform_and_handle_match(line, MatcherMode::FullLine, true)
+    }
+
+    fn complete_block(&mut self, block: PendingBlock) -> io::Result<()> {
+        if let Some(registered) = self.matchers.get_mut(block.matcher_id) {
+            let context = registered.matcher.block_context(&block.lines);
+            registered.on_fired();
+            (registered.on_match)(context)?;
+        }
+        Ok(())
+    }
+
+    /// Output the (consumed) lines of an abandoned block as if they'd never been
+    /// part of one. Matching of the first line resumes where it left off when
+    /// the block started.
+    fn release_block<R: ProcessorOutputReceiver>(
+        &mut self,
+        block: PendingBlock,
+        receiver: &mut R,
+    ) -> io::Result<()> {
+        for (index, line) in block.lines.into_iter().enumerate() {
+            let skip = if index == 0 {
+                &block.start_matched_by[..]
+            } else {
+                &[]
+            };
+            if let Some(to_print) =
+                self.perform_and_handle_match_skipping(line, MatcherMode::FullLine, false, skip)?
+            {
+                receiver.text(to_print)?;
+                receiver.new_line()?;
+            }
+        }
+        Ok(())
     }
 
+    /// If `may_start_block`, multi-line matchers may begin a [PendingBlock] with
+    /// `to_match`. Only one block may be pending at a time.
     fn perform_and_handle_match(
         &mut self,
         to_match: Ansi,
         mode: MatcherMode,
+        may_start_block: bool,
     ) -> io::Result<Option<Ansi>> {
-        match self.perform_match(to_match, mode) {
-            PerformMatchResult::Matched(
-                handler,
-                MatchedResult {
-                    context, remaining, ..
-                },
-            ) => {
-                (handler.on_match)(context)?;
-                Ok(remaining)
+        self.perform_and_handle_match_skipping(to_match, mode, may_start_block, &[])
+    }
+
+    /// Like [Self::perform_and_handle_match], but matchers whose ids are in
+    /// `skip` are not tried
+    fn perform_and_handle_match_skipping(
+        &mut self,
+        mut to_match: Ansi,
+        mode: MatcherMode,
+        mut may_start_block: bool,
+        skip: &[Id],
+    ) -> io::Result<Option<Ansi>> {
+        let mut tried = Vec::new();
+        for m in self.matchers.iter_mut() {
+            if mode < m.mode || skip.contains(&m.id()) {
+                continue;
+            }
+            if may_start_block {
+                tried.push(m.id());
             }
 
-            PerformMatchResult::Ignored(text) => Ok(Some(text)),
+            if m.matcher.is_multi_line() {
+                if may_start_block
+                    && mode == MatcherMode::FullLine
+                    && m.matcher.starts_block(&mut to_match)
+                {
+                    let consume = m.matcher.options.consume;
+                    self.pending_block = Some(PendingBlock {
+                        matcher_id: m.id(),
+                        lines: vec![to_match.clone()],
+                        start_matched_by: std::mem::take(&mut tried),
+                        consume,
+                    });
+                    if consume {
+                        return Ok(None);
+                    }
+                    may_start_block = false;
+                }
+                continue;
+            }
+
+            to_match = match m.matcher.try_match(to_match) {
+                MatchResult::Ignored(ansi) => ansi,
+                MatchResult::Matched(MatchedResult {
+                    context, remaining, ..
+                }) => {
+                    m.on_fired();
+                    (m.on_match)(context)?;
+                    match remaining {
+                        Some(remaining) if m.matcher.options.fallthrough => remaining,
+                        remaining => return Ok(remaining),
+                    }
+                }
+            }
         }
+
+        Ok(Some(to_match))
     }
 
     /// To be called when sending text. More or less simulates
     /// receiving a newline, processing whatever pending line we
     /// had as if it were a full line
     pub fn consume_pending_line(&mut self) -> io::Result<()> {
-        let mut full_line = self.pending_line.take();
+        let full_line = self.pending_line.take();
+        let mut full_line = self.apply_mxp(full_line, true);
 
         self.perform_processing(&mut full_line)?;
-        self.perform_and_handle_match(full_line, MatcherMode::FullLine)?;
+        self.perform_and_handle_match(full_line, MatcherMode::FullLine, false)?;
 
         Ok(())
     }
@@ -286,16 +536,20 @@ impl TextProcessor {
 impl Clearable for TextProcessor {
     fn clear(&mut self) {
         self.matchers.clear();
+        self.pending_block = None;
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::app::matchers::{MatcherOptions, MatcherSpec};
+
     use super::*;
 
     #[derive(Default)]
     struct TextReceiver {
         outputs: Vec<Ansi>,
+        expired: Vec<Id>,
     }
 
     impl ProcessorOutputReceiver for TextReceiver {
@@ -324,7 +578,10 @@ mod tests {
             Ok(())
         }
 
-        fn notification(&mut self, _notification: DaemonNotification) -> io::Result<()> {
+        fn notification(&mut self, notification: DaemonNotification) -> io::Result<()> {
+            if let DaemonNotification::MatcherExpired { matcher_id, .. } = notification {
+                self.expired.push(matcher_id);
+            }
             Ok(())
         }
     }
@@ -369,6 +626,37 @@ mod tests {
         assert_text_eq(&receiver.outputs[1], "Is\n");
     }
 
+    #[test]
+    fn text_processor_skip_consumed_lines() {
+        let mut processor = TextProcessor::default();
+        processor.register_processor(|line| {
+            if line.starts_with("!!") {
+                *line = Ansi::empty();
+            }
+            Ok(())
+        });
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process("!!SOUND(thunder.wav)\r\nIs\n".into(), &mut receiver)
+            .unwrap();
+        assert_eq!(receiver.outputs.len(), 1);
+        assert_text_eq(&receiver.outputs[0], "Is\n");
+    }
+
+    #[test]
+    fn text_processor_mxp() {
+        let mut processor = TextProcessor::default();
+        processor.set_mxp_enabled(true);
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process("Take the <b>sword</b>\r\n".into(), &mut receiver)
+            .unwrap();
+        assert_eq!(receiver.outputs.len(), 1);
+        assert_text_eq(&receiver.outputs[0], "Take the \x1b[1msword\x1b[22m\r\n");
+    }
+
     #[test]
     fn text_processor_invalid_utf8() {
         let mut processor = TextProcessor::default();
@@ -379,4 +667,319 @@ mod tests {
         assert_eq!(receiver.outputs.len(), 1);
         assert_text_eq(&receiver.outputs[0], "You\u{fffd}ve encountered latin1\r\n");
     }
+
+    #[test]
+    fn text_processor_replace_and_unregister_matchers() {
+        let matched = Arc::new(Mutex::new(Vec::new()));
+        let mut processor = TextProcessor::default();
+        let mut register = |replacing: Option<Id>, name: &'static str| {
+            let matched = matched.clone();
+            processor.register_matcher(
+                replacing,
+                None,
+                MatcherSource::Trigger { handler_id: 0 },
+                MatcherSpec::Regex {
+                    options: Default::default(),
+                    source: "fine".to_string(),
+                }
+                .try_into()
+                .unwrap(),
+                MatcherMode::FullLine,
+                move |_| {
+                    matched.lock().unwrap().push(name);
+                    Ok(())
+                },
+            )
+        };
+
+        let first = register(None, "first");
+        register(None, "second");
+        assert_eq!(register(Some(first), "replaced"), first);
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process("Everything is fine\n".into(), &mut receiver)
+            .unwrap();
+        assert_eq!(*matched.lock().unwrap(), vec!["replaced"]);
+
+        assert!(!processor.unregister_matcher(first, MatcherSource::is_prompt));
+        assert!(processor.unregister_matcher(first, |source| !source.is_prompt()));
+        processor
+            .process("Everything is fine\n".into(), &mut receiver)
+            .unwrap();
+        assert_eq!(*matched.lock().unwrap(), vec!["replaced", "second"]);
+    }
+
+    #[test]
+    fn text_processor_priority_and_fallthrough() {
+        let matched = Arc::new(Mutex::new(Vec::new()));
+        let mut processor = TextProcessor::default();
+        let mut register = |name: &'static str, options: MatcherOptions| {
+            let matched = matched.clone();
+            processor.register_matcher(
+                None,
+                None,
+                MatcherSource::Trigger { handler_id: 0 },
+                MatcherSpec::Regex {
+                    options,
+                    source: "fine".to_string(),
+                }
+                .try_into()
+                .unwrap(),
+                MatcherMode::FullLine,
+                move |_| {
+                    matched.lock().unwrap().push(name);
+                    Ok(())
+                },
+            );
+        };
+
+        register("low", Default::default());
+        register(
+            "high",
+            MatcherOptions {
+                priority: 10,
+                fallthrough: true,
+                ..Default::default()
+            },
+        );
+        register("never", Default::default());
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process("Everything is fine\n".into(), &mut receiver)
+            .unwrap();
+        assert_eq!(*matched.lock().unwrap(), vec!["high", "low"]);
+    }
+
+    #[test]
+    fn text_processor_max_fires() {
+        let matched = Arc::new(Mutex::new(Vec::new()));
+        let mut processor = TextProcessor::default();
+        let mut register = |name: &'static str, options: MatcherOptions| {
+            let matched = matched.clone();
+            processor.register_matcher(
+                None,
+                None,
+                MatcherSource::Trigger { handler_id: 0 },
+                MatcherSpec::Regex {
+                    options,
+                    source: "fine".to_string(),
+                }
+                .try_into()
+                .unwrap(),
+                MatcherMode::FullLine,
+                move |_| {
+                    matched.lock().unwrap().push(name);
+                    Ok(())
+                },
+            )
+        };
+
+        let once = register(
+            "once",
+            MatcherOptions {
+                max_fires: Some(1),
+                ..Default::default()
+            },
+        );
+        register("always", Default::default());
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process("Everything is fine\nStill fine\n".into(), &mut receiver)
+            .unwrap();
+        assert_eq!(*matched.lock().unwrap(), vec!["once", "always"]);
+        assert_eq!(receiver.expired, vec![once]);
+        assert!(!processor.unregister_matcher(once, |_| true));
+    }
+
+    fn register_multi_line(
+        processor: &mut TextProcessor,
+        spec: MatcherSpec,
+    ) -> Arc<Mutex<Vec<MatchContext>>> {
+        let matched = Arc::new(Mutex::new(Vec::new()));
+        let on_match = matched.clone();
+        processor.register_matcher(
+            None,
+            None,
+            MatcherSource::Trigger { handler_id: 0 },
+            spec.try_into().unwrap(),
+            MatcherMode::FullLine,
+            move |context| {
+                on_match.lock().unwrap().push(context);
+                Ok(())
+            },
+        );
+        matched
+    }
+
+    #[test]
+    fn text_processor_multi_line_consume() {
+        let mut processor = TextProcessor::default();
+        let matched = register_multi_line(
+            &mut processor,
+            MatcherSpec::MultiLine {
+                options: MatcherOptions {
+                    consume: true,
+                    ..Default::default()
+                },
+                start: "^Score: (?P<name>\\w+)".to_string(),
+                continuation: Some("^  HP: (?P<hp>\\d+)".to_string()),
+                end: Some("^--$".to_string()),
+                max_lines: 10,
+            },
+        );
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process(
+                "Before\r\nScore: Grayskull\r\n  HP: 42\r\n--\r\nAfter\r\n".into(),
+                &mut receiver,
+            )
+            .unwrap();
+
+        assert_eq!(receiver.outputs.len(), 2);
+        assert_text_eq(&receiver.outputs[0], "Before\r\n");
+        assert_text_eq(&receiver.outputs[1], "After\r\n");
+
+        let matched = matched.lock().unwrap();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(
+            matched[0].indexed[&0].plain,
+            "Score: Grayskull\n  HP: 42\n--"
+        );
+        assert_eq!(matched[0].indexed[&1].plain, "Grayskull");
+        assert_eq!(matched[0].named["name"].plain, "Grayskull");
+        assert_eq!(matched[0].named["hp"].plain, "42");
+    }
+
+    #[test]
+    fn text_processor_multi_line_abandoned() {
+        let mut processor = TextProcessor::default();
+        let matched = register_multi_line(
+            &mut processor,
+            MatcherSpec::MultiLine {
+                options: MatcherOptions {
+                    consume: true,
+                    ..Default::default()
+                },
+                start: "^Score:".to_string(),
+                continuation: None,
+                end: Some("^--$".to_string()),
+                max_lines: 3,
+            },
+        );
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process("Score:\nA\nB\nC\n".into(), &mut receiver)
+            .unwrap();
+
+        assert!(matched.lock().unwrap().is_empty());
+        assert_eq!(receiver.outputs.len(), 4);
+        assert_text_eq(&receiver.outputs[0], "Score:\n");
+        assert_text_eq(&receiver.outputs[1], "A\n");
+        assert_text_eq(&receiver.outputs[2], "B\n");
+        assert_text_eq(&receiver.outputs[3], "C\n");
+    }
+
+    #[test]
+    fn text_processor_multi_line_abandoned_after_higher_priority() {
+        let mut processor = TextProcessor::default();
+        let block = register_multi_line(
+            &mut processor,
+            MatcherSpec::MultiLine {
+                options: MatcherOptions {
+                    consume: true,
+                    ..Default::default()
+                },
+                start: "^Score:".to_string(),
+                continuation: None,
+                end: Some("^--$".to_string()),
+                max_lines: 2,
+            },
+        );
+
+        let matched = Arc::new(Mutex::new(Vec::new()));
+        let mut register = |name: &'static str, options: MatcherOptions| {
+            let matched = matched.clone();
+            processor.register_matcher(
+                None,
+                None,
+                MatcherSource::Trigger { handler_id: 0 },
+                MatcherSpec::Regex {
+                    options,
+                    source: "^Score:".to_string(),
+                }
+                .try_into()
+                .unwrap(),
+                MatcherMode::FullLine,
+                move |_| {
+                    matched.lock().unwrap().push(name);
+                    Ok(())
+                },
+            )
+        };
+
+        let high = register(
+            "high",
+            MatcherOptions {
+                priority: 10,
+                fallthrough: true,
+                max_fires: Some(2),
+                ..Default::default()
+            },
+        );
+        register("low", Default::default());
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process("Score:\nA\nB\n".into(), &mut receiver)
+            .unwrap();
+
+        // The higher-priority trigger already saw the first line before the block
+        // started, so only the lower-priority one sees it once the block is abandoned
+        assert!(block.lock().unwrap().is_empty());
+        assert_eq!(*matched.lock().unwrap(), vec!["high", "low"]);
+        assert!(receiver.expired.is_empty());
+        assert_eq!(receiver.outputs.len(), 3);
+        assert_text_eq(&receiver.outputs[0], "Score:\n");
+
+        processor
+            .process("Score:\nA\nB\n".into(), &mut receiver)
+            .unwrap();
+        assert_eq!(*matched.lock().unwrap(), vec!["high", "low", "high", "low"]);
+        assert_eq!(receiver.expired, vec![high]);
+    }
+
+    #[test]
+    fn text_processor_multi_line_continuation() {
+        let mut processor = TextProcessor::default();
+        let matched = register_multi_line(
+            &mut processor,
+            MatcherSpec::MultiLine {
+                options: Default::default(),
+                start: "^Players:".to_string(),
+                continuation: Some("^  ".to_string()),
+                end: None,
+                max_lines: 10,
+            },
+        );
+
+        let mut receiver = TextReceiver::default();
+        processor
+            .process("Players:\n  Adora\n  Adam\n".into(), &mut receiver)
+            .unwrap();
+
+        // Without `consume`, lines are output as they arrive
+        assert_eq!(receiver.outputs.len(), 3);
+        assert!(matched.lock().unwrap().is_empty());
+
+        // ... and, since there's no `end`, the block completes once the server's done
+        processor.on_end_of_prompt(&mut receiver).unwrap();
+        let matched = matched.lock().unwrap();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].indexed[&0].plain, "Players:\n  Adora\n  Adam");
+    }
 }
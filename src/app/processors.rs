@@ -1,6 +1,10 @@
-use crate::daemon::{channel::RespondedConnectionChannel, handlers::set_prompt_content};
+use crate::daemon::{
+    channel::{ConnectionNotifier, RespondedConnectionChannel},
+    handlers::set_prompt_content,
+    notifications::DaemonNotification,
+};
 
-use super::{connections::ConnectionReceiver, LockableState};
+use super::{connections::ConnectionReceiver, processing::msp, LockableState};
 
 pub fn register_processors(
     state: LockableState,
@@ -28,6 +32,16 @@ pub fn register_processors(
         Ok(())
     });
 
+    let msp_receiver = receiver.clone();
+    processor.register_processor(move |line| {
+        for event in msp::extract_triggers(line) {
+            msp_receiver
+                .clone()
+                .notify(DaemonNotification::Event(event));
+        }
+        Ok(())
+    });
+
     processor.register_auto_prompt_processor(move |line| {
         let mut my_receiver = receiver.clone();
         set_prompt_content::try_handle(
@@ -55,6 +55,19 @@ pub enum Commands {
     ReplayDump {
         path: PathBuf,
 
+        /// Replay with the recorded timing, sped up (or slowed down) by this factor.
+        /// Implies --realtime
+        #[arg(long)]
+        speed: Option<f64>,
+
+        /// Replay with the recorded timing, instead of as fast as possible
+        #[arg(long)]
+        realtime: bool,
+
+        /// Fast-forward through the first this-many seconds of the recording
+        #[arg(long, default_value_t = 0.0)]
+        seek: f64,
+
         #[clap(subcommand)]
         ui: Option<UiConfig>,
     },
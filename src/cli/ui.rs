@@ -30,12 +30,26 @@ use crate::{
 
 use self::prompts::{PromptGroups, PromptsState};
 
-#[derive(Default)]
 pub struct UiState {
     pub prompts: PromptsState,
     pub active_prompt_group: Id,
     pub inactive_prompt_groups: PromptGroups,
     pub is_auto_prompt_enabled: bool,
+    pub is_go_ahead_prompt_enabled: bool,
+    pub is_server_echo_enabled: bool,
+}
+
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            prompts: Default::default(),
+            active_prompt_group: Default::default(),
+            inactive_prompt_groups: Default::default(),
+            is_auto_prompt_enabled: false,
+            is_go_ahead_prompt_enabled: true,
+            is_server_echo_enabled: false,
+        }
+    }
 }
 
 impl Clearable for UiState {
@@ -7,6 +7,7 @@ use crate::{
     app::{
         processing::{
             ansi::Ansi,
+            mxp::{extract_links, ExtractedLinks},
             text::{
                 ProcessorOutputReceiver, ProcessorOutputReceiverFactory, SystemMessage,
                 WindowSizeSource,
@@ -65,10 +66,12 @@ impl ProcessorOutputReceiver for ExternalUI {
         self.send_external_ui(ExternalUINotification::ClearPartialLine)
     }
 
-    fn text(&mut self, mut text: Ansi) -> std::io::Result<()> {
+    fn text(&mut self, text: Ansi) -> std::io::Result<()> {
+        let ExtractedLinks { ansi, plain, links } = extract_links(&text);
         self.send_external_ui(ExternalUINotification::Text {
-            ansi: text.to_string(),
-            plain: Some(text.strip_ansi().to_string()),
+            ansi,
+            plain: Some(plain),
+            links,
         })
     }
 
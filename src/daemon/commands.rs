@@ -1,3 +1,5 @@
+use std::{collections::HashMap, time::Duration};
+
 use serde::Deserialize;
 
 use crate::{
@@ -6,6 +8,7 @@ use crate::{
         matchers::MatcherSpec, Id,
     },
     daemon::protocol::replay::ReplayConfig,
+    transport::EventData,
 };
 
 use super::protocol::cursors::HistoryCursor;
@@ -15,7 +18,7 @@ pub struct Connect {
     pub uri: String,
 
     #[serde(flatten)]
-    pub config: Option<ConnectionConfig>,
+    pub config: Option<Box<ConnectionConfig>>,
 
     // This is for internal use only; it should not actually be sent
     #[serde(skip)]
@@ -29,9 +32,134 @@ pub enum AliasReplacement {
     Simple { replacement_pattern: FormatterSpec },
 }
 
+/// Options common to RegisterAlias, RegisterTrigger, and RegisterPrompt
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct MatcherRegistration {
+    /// If provided and it refers to an existing matcher of the same kind, that
+    /// matcher is replaced in place (keeping its position and id); otherwise,
+    /// a new matcher is registered with a new id.
+    pub matcher_id: Option<Id>,
+
+    /// If provided, the matcher may be enabled or disabled along with the rest
+    /// of its group via `SetMatcherGroupEnabled`
+    pub group: Option<String>,
+}
+
+fn default_reconnect_initial_delay_ms() -> u64 {
+    1000
+}
+
+fn default_reconnect_max_delay_ms() -> u64 {
+    60_000
+}
+
+/// Opt-in policy for automatically reconnecting when a connection is lost
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// How long to wait before the first attempt. Each subsequent attempt waits
+    /// twice as long as the last, up to `max_delay_ms`. Both must be positive.
+    #[serde(default = "default_reconnect_initial_delay_ms")]
+    pub initial_delay_ms: u64,
+
+    #[serde(default = "default_reconnect_max_delay_ms")]
+    pub max_delay_ms: u64,
+
+    /// Give up after this many failed attempts. If not provided, we will keep trying
+    /// until the connection is explicitly disconnected.
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    /// The delay before the given (1-based) attempt
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let factor = 1u64
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        let delay = self.initial_delay_ms.saturating_mul(factor);
+        Duration::from_millis(delay.min(self.max_delay_ms))
+    }
+}
+
+/// A config value that, once set, may be turned off again by providing `false`
+/// in its place
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Disableable<T> {
+    Disabled(Disabled),
+    Enabled(T),
+}
+
+impl<T> Disableable<T> {
+    pub fn enabled(&self) -> Option<&T> {
+        match self {
+            Disableable::Disabled(_) => None,
+            Disableable::Enabled(value) => Some(value),
+        }
+    }
+}
+
+/// Only deserializable from `false`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disabled;
+
+impl<'de> Deserialize<'de> for Disabled {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match bool::deserialize(deserializer)? {
+            false => Ok(Disabled),
+            true => Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Bool(true),
+                &"false",
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct KeepalivePolicy {
+    /// Send a keepalive after this long without sending anything to the server.
+    /// Must be positive.
+    pub interval_ms: u64,
+
+    /// A command to send as the keepalive (IE: "look"). If not provided, a telnet
+    /// NOP is sent instead, which servers will not treat as input.
+    pub command: Option<String>,
+}
+
 #[derive(Debug, Default, Deserialize, PartialEq, Eq)]
 pub struct ConnectionConfig {
     pub auto_prompts: Option<bool>,
+
+    /// If `true` or not provided, a telnet Go Ahead will be treated like an EOR when
+    /// using `auto_prompts`, unless the server has negotiated SuppressGoAhead.
+    pub go_ahead_prompts: Option<bool>,
+
+    /// The label of the encoding the server uses for text (eg: "latin1" or "cp437").
+    /// Defaults to UTF-8. Servers that support CHARSET negotiation may override this.
+    pub encoding: Option<String>,
+
+    /// Extra NEW-ENVIRON variables to provide to the server (IE: MNES variables like
+    /// "IPADDRESS"), or overrides for the defaults (like "CLIENT_NAME").
+    pub environment: Option<HashMap<String, String>>,
+
+    /// If provided, automatically reconnect (keeping the same connection_id and all
+    /// of its registered state) when the connection is lost unexpectedly. While
+    /// waiting to reconnect, text and events can't be sent, so `Send` and `SendEvent`
+    /// respond with `sent: false`.
+    pub reconnect: Option<ReconnectPolicy>,
+
+    /// How long to wait for a connection to be established before giving up.
+    /// Defaults to 30 seconds.
+    pub connect_timeout_ms: Option<u64>,
+
+    /// If provided, send something to the server after a period of silence, so
+    /// servers (or NAT gateways) that drop idle sessions don't drop ours. Provide
+    /// `false` to stop sending keepalives.
+    pub keepalive: Option<Disableable<KeepalivePolicy>>,
+
+    /// If provided, send an Idle notification once nothing has been received from
+    /// the server for this long. Must be positive; provide `false` to stop sending
+    /// Idle notifications.
+    pub idle_notify_ms: Option<Disableable<u64>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,11 +179,29 @@ pub enum ClientRequest {
         persist: Option<bool>,
     },
 
+    /// Send an out-of-band event to the server over a supported protocol. `ns` selects
+    /// the protocol (IE: "GMCP" or "MSDP"); for MSDP, `name` is the variable or command
+    /// (IE: "REPORT") and `payload` its value(s); for GMCP, `name` is the package and
+    /// message (IE: "Char.Items.Inv") and `payload` is its (optional) JSON body.
+    SendEvent {
+        connection_id: Id,
+
+        #[serde(flatten)]
+        event: EventData,
+    },
+
+    /// Open a short-lived connection to `uri` just to collect its MSSP server status
+    /// (player count, codebase, etc.), then disconnect. Responds with `MsspResult`, whose
+    /// `status` will be null if the server doesn't support MSSP.
+    QueryMssp {
+        uri: String,
+    },
+
     ConfigureConnection {
         connection_id: Id,
 
         #[serde(flatten)]
-        config: ConnectionConfig,
+        config: Box<ConnectionConfig>,
     },
 
     GetHistory {
@@ -79,18 +225,25 @@ pub enum ClientRequest {
         params: CompletionParams,
     },
 
+    /// Responds with `MatcherRegistered`, as do [RegisterTrigger] and [RegisterPrompt].
+    /// See [MatcherRegistration] for replacing existing matchers and grouping them.
     RegisterAlias {
         connection_id: Id,
         matcher: MatcherSpec,
 
         #[serde(flatten)]
         replacement: AliasReplacement,
+
+        #[serde(flatten)]
+        registration: MatcherRegistration,
     },
 
     RegisterTrigger {
         connection_id: Id,
         matcher: MatcherSpec,
         handler_id: Id,
+        #[serde(flatten)]
+        registration: MatcherRegistration,
     },
 
     /// This is provided as a convenience for declaring a Prompt line that directly renders
@@ -102,6 +255,35 @@ pub enum ClientRequest {
         matcher: MatcherSpec,
         group_id: Id,
         prompt_index: usize,
+        #[serde(flatten)]
+        registration: MatcherRegistration,
+    },
+
+    /// Remove a single matcher previously registered via [RegisterAlias]. The
+    /// same applies to [UnregisterTrigger] and [UnregisterPrompt], which each
+    /// only remove matchers of their own kind.
+    UnregisterAlias {
+        connection_id: Id,
+        matcher_id: Id,
+    },
+
+    UnregisterTrigger {
+        connection_id: Id,
+        matcher_id: Id,
+    },
+
+    UnregisterPrompt {
+        connection_id: Id,
+        matcher_id: Id,
+    },
+
+    /// Enable or disable every alias, trigger, and prompt registered with the
+    /// given `group`. Groups are enabled by default; a disabled group stays
+    /// disabled for matchers registered to it later.
+    SetMatcherGroupEnabled {
+        connection_id: Id,
+        group: String,
+        enabled: bool,
     },
 
     // Set the content of a Prompt line. A Prompt line is uniquely identified by the tuple
@@ -141,3 +323,40 @@ pub enum ClientNotification {
         connection_id: Id,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disableable_config_test() {
+        let config: ConnectionConfig = serde_json::from_str(
+            r#"{"keepalive": {"interval_ms": 1000}, "idle_notify_ms": false}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.keepalive,
+            Some(Disableable::Enabled(KeepalivePolicy {
+                interval_ms: 1000,
+                command: None,
+            }))
+        );
+        assert_eq!(config.idle_notify_ms, Some(Disableable::Disabled(Disabled)));
+
+        assert!(serde_json::from_str::<ConnectionConfig>(r#"{"keepalive": true}"#).is_err());
+    }
+
+    #[test]
+    fn reconnect_backoff_test() {
+        let policy = ReconnectPolicy {
+            initial_delay_ms: 500,
+            max_delay_ms: 3000,
+            max_attempts: None,
+        };
+        assert_eq!(policy.delay_for_attempt(1), Duration::from_millis(500));
+        assert_eq!(policy.delay_for_attempt(2), Duration::from_millis(1000));
+        assert_eq!(policy.delay_for_attempt(3), Duration::from_millis(2000));
+        assert_eq!(policy.delay_for_attempt(4), Duration::from_millis(3000));
+        assert_eq!(policy.delay_for_attempt(100), Duration::from_millis(3000));
+    }
+}
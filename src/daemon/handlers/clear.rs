@@ -8,5 +8,6 @@ pub async fn handle(mut state: LockableState, connection_id: Id) {
         return;
     };
     state.processor.lock().unwrap().clear();
+    state.send_processor.lock().await.clear();
     state.ui_state.lock().unwrap().clear();
 }
@@ -1,15 +1,93 @@
-use std::io;
+use std::{io, time::Duration};
 
 use crate::{
-    app::{connections::ConnectionState, Id, LockableState},
+    app::{
+        connections::{ConnectionState, Outgoing},
+        Id, LockableState,
+    },
     daemon::{channel::Channel, commands::ConnectionConfig, responses::DaemonResponse},
+    net::encoding::TextEncoding,
 };
 
-pub fn apply_config(connection: &mut ConnectionState, config: &ConnectionConfig) {
+pub fn apply_config(connection: &mut ConnectionState, config: &ConnectionConfig) -> io::Result<()> {
+    validate_config(config)?;
+
+    if let Some(label) = &config.encoding {
+        let Some(encoding) = TextEncoding::for_label(label) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported encoding: {label}"),
+            ));
+        };
+        *connection.encoding.lock().unwrap() = encoding;
+    }
+
+    if let Some(environment) = &config.environment {
+        connection
+            .environment
+            .lock()
+            .unwrap()
+            .extend(environment.clone());
+    }
+
+    if let Some(reconnect) = &config.reconnect {
+        *connection.reconnect.lock().unwrap() = Some(reconnect.clone());
+    }
+
+    if let Some(timeout) = config.connect_timeout_ms {
+        *connection.connect_timeout.lock().unwrap() = Some(Duration::from_millis(timeout));
+    }
+
+    if let Some(keepalive) = &config.keepalive {
+        *connection.keepalive.lock().unwrap() = keepalive.enabled().cloned();
+    }
+
+    if let Some(idle) = &config.idle_notify_ms {
+        *connection.idle_notify.lock().unwrap() =
+            idle.enabled().copied().map(Duration::from_millis);
+    }
+
     let mut ui_state = connection.ui_state.lock().unwrap();
     if let Some(enable_auto_prompts) = config.auto_prompts {
         ui_state.is_auto_prompt_enabled = enable_auto_prompts;
     }
+    if let Some(enable_go_ahead_prompts) = config.go_ahead_prompts {
+        ui_state.is_go_ahead_prompt_enabled = enable_go_ahead_prompts;
+    }
+
+    Ok(())
+}
+
+/// Reject values that would make no sense, before any of `config` is applied
+fn validate_config(config: &ConnectionConfig) -> io::Result<()> {
+    if config
+        .reconnect
+        .as_ref()
+        .is_some_and(|policy| policy.initial_delay_ms == 0 || policy.max_delay_ms == 0)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "reconnect initial_delay_ms and max_delay_ms must be positive",
+        ));
+    }
+
+    let keepalive = config.keepalive.as_ref().and_then(|it| it.enabled());
+    if keepalive.is_some_and(|keepalive| keepalive.interval_ms == 0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "keepalive interval_ms must be positive",
+        ));
+    }
+
+    let idle_notify_ms = config.idle_notify_ms.as_ref().and_then(|it| it.enabled());
+    if idle_notify_ms == Some(&0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "idle_notify_ms must be positive",
+        ));
+    }
+
+    Ok(())
 }
 
 pub async fn handle(
@@ -18,16 +96,104 @@ pub async fn handle(
     connection_id: Id,
     config: ConnectionConfig,
 ) -> io::Result<()> {
-    let mut state = state.lock().unwrap();
-    let Some(mut connection) = state.connections.get_state(connection_id) else {
+    let (connection, outbox) = {
+        let mut state = state.lock().unwrap();
+        (
+            state.connections.get_state(connection_id),
+            state.connections.get_outbox(connection_id),
+        )
+    };
+    let (Some(mut connection), Some(outbox)) = (connection, outbox) else {
         channel.respond(DaemonResponse::ErrorResult {
             error: format!("Invalid connection ID {connection_id}"),
         });
         return Ok(());
     };
 
-    apply_config(&mut connection, &config);
+    if let Err(e) = apply_config(&mut connection, &config) {
+        channel.respond(DaemonResponse::ErrorResult {
+            error: e.to_string(),
+        });
+        return Ok(());
+    }
+
+    if config.environment.is_some() {
+        let environment = connection.environment.lock().unwrap().clone();
+        outbox.send(Outgoing::Environment(environment)).await.ok();
+    }
 
     channel.respond(DaemonResponse::OkResult);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::daemon::commands::{Disableable, Disabled, KeepalivePolicy, ReconnectPolicy};
+
+    use super::*;
+
+    fn keepalive(interval_ms: u64) -> ConnectionConfig {
+        ConnectionConfig {
+            keepalive: Some(Disableable::Enabled(KeepalivePolicy {
+                interval_ms,
+                command: Some("look".to_string()),
+            })),
+            idle_notify_ms: Some(Disableable::Enabled(5000)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reject_zero_intervals_test() {
+        let mut connection = ConnectionState::default();
+        assert!(apply_config(&mut connection, &keepalive(0)).is_err());
+        assert!(connection.keepalive_policy().is_none());
+        assert!(connection.idle_notify().is_none());
+
+        let config = ConnectionConfig {
+            idle_notify_ms: Some(Disableable::Enabled(0)),
+            ..Default::default()
+        };
+        assert!(apply_config(&mut connection, &config).is_err());
+        assert!(connection.idle_notify().is_none());
+    }
+
+    #[test]
+    fn reject_zero_reconnect_delay_test() {
+        let mut connection = ConnectionState::default();
+        let config = ConnectionConfig {
+            reconnect: Some(ReconnectPolicy {
+                initial_delay_ms: 0,
+                max_delay_ms: 1000,
+                max_attempts: None,
+            }),
+            ..Default::default()
+        };
+        assert!(apply_config(&mut connection, &config).is_err());
+        assert!(connection.reconnect_policy().is_none());
+    }
+
+    #[test]
+    fn disable_keepalive_test() -> io::Result<()> {
+        let mut connection = ConnectionState::default();
+        apply_config(&mut connection, &keepalive(1000))?;
+        assert!(connection.keepalive_policy().is_some());
+        assert_eq!(connection.idle_notify(), Some(Duration::from_secs(5)));
+
+        // Leaving them out changes nothing...
+        apply_config(&mut connection, &ConnectionConfig::default())?;
+        assert!(connection.keepalive_policy().is_some());
+
+        // ... but they can be explicitly disabled:
+        let config = ConnectionConfig {
+            keepalive: Some(Disableable::Disabled(Disabled)),
+            idle_notify_ms: Some(Disableable::Disabled(Disabled)),
+            ..Default::default()
+        };
+        apply_config(&mut connection, &config)?;
+        assert!(connection.keepalive_policy().is_none());
+        assert!(connection.idle_notify().is_none());
+
+        Ok(())
+    }
+}
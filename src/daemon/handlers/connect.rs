@@ -1,10 +1,4 @@
-use std::{
-    env,
-    fs::File,
-    future,
-    io::{self, Write},
-    sync::Mutex,
-};
+use std::{env, future, io, sync::Mutex, time::Duration};
 
 use bytes::Bytes;
 use crossterm::{
@@ -12,10 +6,11 @@ use crossterm::{
     terminal,
 };
 use futures::{FutureExt as _, StreamExt as _};
+use tokio::time::{self, Instant};
 
 use crate::{
     app::{
-        connections::{ConnectionReceiver, Outgoing},
+        connections::{ConnectionReceiver, ConnectionState, Outgoing},
         processing::text::{
             ProcessorOutputReceiver, ProcessorOutputReceiverFactory, SystemMessage, TextProcessor,
             WindowSizeSource,
@@ -24,22 +19,28 @@ use crate::{
         LockableState,
     },
     daemon::{
-        channel::Channel, commands, notifications::DaemonNotification, responses::DaemonResponse,
+        channel::Channel,
+        commands::{self, KeepalivePolicy, ReconnectPolicy},
+        notifications::{DaemonNotification, DisconnectReason},
+        responses::DaemonResponse,
     },
     net::Uri,
-    transport::{BoxedTransport, Transport, TransportEvent, TransportNotification},
+    transport::{
+        recording::Recorder, BoxedTransport, ConnectOptions, ConnectStatus, Transport,
+        TransportEvent, TransportNotification,
+    },
 };
 
 use super::configure_connection::apply_config;
 
 pub async fn process_connection<T: Transport, R: ProcessorOutputReceiver>(
     mut transport: T,
-    mut connection: ConnectionReceiver,
+    connection: &mut ConnectionReceiver,
     receiver: &mut R,
 ) -> io::Result<()> {
-    let mut dump = if let Ok(filename) = env::var("KODACHI_DUMP") {
+    let mut recording = if let Ok(filename) = env::var("KODACHI_DUMP") {
         if !filename.is_empty() {
-            Some(File::options().append(true).create(true).open(filename)?)
+            Some(Recorder::append_to(filename)?)
         } else {
             None
         }
@@ -48,6 +49,7 @@ pub async fn process_connection<T: Transport, R: ProcessorOutputReceiver>(
     };
 
     let mut connected = true;
+    let mut decoder = connection.state.encoding().decoder();
 
     // NOTE: It's a bit hacky to do it this way... but it's also
     // much simpler than introducing some kind of boxed type
@@ -61,7 +63,16 @@ pub async fn process_connection<T: Transport, R: ProcessorOutputReceiver>(
                 .await?;
             Some(EventStream::new())
         }
-        Some(WindowSizeSource::External) => None,
+        Some(WindowSizeSource::External) => {
+            // Restore the size from any previous session
+            let window_size = *connection.state.window_size.lock().unwrap();
+            if let Some((width, height)) = window_size {
+                transport
+                    .notify(TransportNotification::WindowSize { width, height })
+                    .await?;
+            }
+            None
+        }
         None => {
             // Also, tell the Naws handler we don't support it:
             transport
@@ -71,18 +82,40 @@ pub async fn process_connection<T: Transport, R: ProcessorOutputReceiver>(
         }
     };
 
+    let mut environment = connection.state.environment.lock().unwrap().clone();
+    environment
+        .entry("CHARSET".to_string())
+        .or_insert_with(|| connection.state.encoding().name().to_string());
+    transport
+        .notify(TransportNotification::Environment(environment))
+        .await?;
+
+    let mut activity = Activity::default();
+
     while connected {
         let window_size_event = window_size_stream
             .as_mut()
             .map(|stream| stream.next().boxed().fuse())
             .unwrap_or_else(|| future::pending().boxed().fuse());
 
+        let keepalive = connection.state.keepalive_policy();
+        let idle_notify = connection.state.idle_notify();
+        let activity_deadline = activity.next_deadline(keepalive.as_ref(), idle_notify);
+        let activity_timer = async move {
+            match activity_deadline {
+                Some(deadline) => time::sleep_until(deadline).await,
+                None => future::pending().await,
+            }
+        };
+
         tokio::select! {
-            incoming = transport.read() => match incoming? {
+            incoming = transport.read() => match activity.on_received(record_inbound(&mut recording, incoming?)?) {
                 TransportEvent::Data(data) => {
-                    if let Some(f) = &mut dump {
-                        f.write_all(&data)?;
+                    let encoding = connection.state.encoding();
+                    if decoder.encoding() != encoding {
+                        decoder = encoding.decoder();
                     }
+                    let data = decoder.decode(data);
 
                     let processor = &connection
                         .state
@@ -103,25 +136,73 @@ pub async fn process_connection<T: Transport, R: ProcessorOutputReceiver>(
                     }
                 },
 
+                TransportEvent::ServerEcho { enabled } => {
+                    connection.state.ui_state.lock().unwrap().is_server_echo_enabled = enabled;
+                    receiver.notification(DaemonNotification::PasswordMode { enabled })?;
+                },
+
+                TransportEvent::GoAhead => {
+                    if connection.state.is_auto_prompt_enabled()
+                        && connection.state.is_go_ahead_prompt_enabled() {
+                        let processor = &connection
+                            .state
+                            .processor;
+                        handle_end_of_prompt(receiver, processor)?;
+                    }
+                },
+
+                TransportEvent::EncodingChanged(encoding) => {
+                    *connection.state.negotiated_encoding.lock().unwrap() = Some(encoding);
+                },
+
+                TransportEvent::Mxp { enabled } => {
+                    connection.state.processor.lock().unwrap().set_mxp_enabled(enabled);
+                },
+
                 TransportEvent::Nop => {},
             },
 
             outgoing = connection.outbox.recv() => {
                 match outgoing {
                     Some(Outgoing::Text(text)) => {
-                        transport.write(text.as_bytes()).await?;
-                        transport.write(b"\r\n").await?;
-
-                        // Also print locally
+                        // NOTE: Written together so message-based transports (IE: WebSocket)
+                        // send the whole line as a single message
+                        let encoded = connection.state.encoding().encode(&format!("{text}\r\n"));
+                        transport.write(&encoded).await?;
+                        if let Some(recording) = &mut recording {
+                            recording.outbound_data(&encoded)?;
+                        }
+                        activity.on_sent();
+
+                        // Also print locally (unless the server is handling echo)
+                        let echo = !connection.state.is_server_echo_enabled();
                         let processor = &connection
                             .state
                             .processor;
-                        handle_sent_text(receiver, processor, text)?;
+                        handle_sent_text(receiver, processor, text, echo)?;
+                    }
+                    Some(Outgoing::Event(event)) => {
+                        if let Some(recording) = &mut recording {
+                            recording.outbound_event(&event)?;
+                        }
+                        activity.on_sent();
+                        match transport.send_event(event).await {
+                            Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+                                log::trace!(target: "transport", "Unable to send event: {err}");
+                            }
+                            result => result?,
+                        }
+                    }
+                    Some(Outgoing::Environment(environment)) => {
+                        transport.notify(TransportNotification::Environment(environment)).await?;
                     }
                     Some(Outgoing::WindowSize { width, height }) => {
+                        *connection.state.window_size.lock().unwrap() = Some((width, height));
                         transport.notify(TransportNotification::WindowSize {width, height}).await?;
                     }
                     Some(Outgoing::Disconnect) | None => {
+                        // We're leaving anyway; errors closing don't matter much
+                        transport.close().await.ok();
                         connected = false;
                     }
                 };
@@ -131,12 +212,109 @@ pub async fn process_connection<T: Transport, R: ProcessorOutputReceiver>(
                 transport.notify(TransportNotification::WindowSize {width, height}).await?;
             },
 
+            _ = activity_timer => {
+                if let Some(keepalive) = keepalive.filter(|policy| activity.is_keepalive_due(policy)) {
+                    match keepalive.command {
+                        Some(command) => {
+                            let encoded = connection.state.encoding().encode(&format!("{command}\r\n"));
+                            transport.write(&encoded).await?;
+                            if let Some(recording) = &mut recording {
+                                recording.outbound_data(&encoded)?;
+                            }
+                        }
+                        None => match transport.send_keepalive().await {
+                            Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+                                log::trace!(target: "transport", "Unable to send keepalive: {err}");
+                            }
+                            result => result?,
+                        },
+                    }
+                    activity.on_sent();
+                }
+
+                if let Some(seconds) = idle_notify.and_then(|idle| activity.take_idle(idle)) {
+                    receiver.notification(DaemonNotification::Idle { seconds })?;
+                }
+            },
+
         };
     }
 
     Ok(())
 }
 
+/// Tracks when we last sent or received anything, for keepalives and idle notifications
+struct Activity {
+    last_sent: Instant,
+    last_received: Instant,
+    idle_notified: bool,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            last_sent: Instant::now(),
+            last_received: Instant::now(),
+            idle_notified: false,
+        }
+    }
+}
+
+impl Activity {
+    fn on_sent(&mut self) {
+        self.last_sent = Instant::now();
+    }
+
+    /// Note that `event` was received, returning it for processing
+    fn on_received(&mut self, event: TransportEvent) -> TransportEvent {
+        if !matches!(event, TransportEvent::Nop) {
+            self.last_received = Instant::now();
+            self.idle_notified = false;
+        }
+        event
+    }
+
+    /// When we next need to send a keepalive or notify of idleness, if ever
+    fn next_deadline(
+        &self,
+        keepalive: Option<&KeepalivePolicy>,
+        idle_notify: Option<Duration>,
+    ) -> Option<Instant> {
+        let keepalive =
+            keepalive.map(|policy| self.last_sent + Duration::from_millis(policy.interval_ms));
+        let idle = idle_notify
+            .filter(|_| !self.idle_notified)
+            .map(|idle| self.last_received + idle);
+        keepalive.into_iter().chain(idle).min()
+    }
+
+    fn is_keepalive_due(&self, policy: &KeepalivePolicy) -> bool {
+        self.last_sent.elapsed() >= Duration::from_millis(policy.interval_ms)
+    }
+
+    /// If we've been idle for at least `idle` (and haven't said so yet), returns
+    /// how many seconds it's been
+    fn take_idle(&mut self, idle: Duration) -> Option<u64> {
+        let elapsed = self.last_received.elapsed();
+        if self.idle_notified || elapsed < idle {
+            return None;
+        }
+        self.idle_notified = true;
+        Some(elapsed.as_secs())
+    }
+}
+
+/// Record `event` (if we're recording), returning it for processing
+fn record_inbound(
+    recording: &mut Option<Recorder>,
+    event: TransportEvent,
+) -> io::Result<TransportEvent> {
+    if let Some(recording) = recording {
+        recording.inbound(&event)?;
+    }
+    Ok(event)
+}
+
 pub async fn handle<TUI: ProcessorOutputReceiverFactory>(
     ui: TUI,
     channel: Channel,
@@ -146,8 +324,14 @@ pub async fn handle<TUI: ProcessorOutputReceiverFactory>(
     let mut connection = state.lock().unwrap().connections.create();
     let connection_id = connection.id;
 
-    if let Some(config) = data.config {
-        apply_config(&mut connection.state, &config);
+    if let Some(config) = data.config.take() {
+        if let Err(e) = apply_config(&mut connection.state, &config) {
+            state.lock().unwrap().connections.drop(connection_id);
+            channel.respond(DaemonResponse::ErrorResult {
+                error: e.to_string(),
+            });
+            return Ok(());
+        }
     }
 
     let notifier = channel.respond(DaemonResponse::Connecting { connection_id });
@@ -155,55 +339,230 @@ pub async fn handle<TUI: ProcessorOutputReceiverFactory>(
     let mut receiver = ui.create(receiver_state.clone(), connection_id, notifier.clone());
     let processor_receiver = notifier.for_connection(connection_id);
 
-    let transport = if let Some(replay) = data.replay.take() {
-        BoxedTransport::replay(replay, 4096).await?
+    register_processors(state.clone(), &mut connection, processor_receiver);
+
+    let disconnected = match connect_and_process(&mut connection, data, &mut receiver).await {
+        Ok(disconnected) => disconnected,
+        Err(error) => {
+            log::trace!(target: "transport", "Connection {connection_id} ended: {error}");
+            DaemonNotification::disconnected_by(&error)
+        }
+    };
+
+    // NOTE: The receiver may be what failed in the first place, so there's not
+    // much we can do if this fails; we still need to clean up below.
+    receiver.notification(disconnected).ok();
+    state.lock().unwrap().connections.drop(connection_id);
+
+    Ok(())
+}
+
+/// Connect to the requested uri and process the connection until it ends, reconnecting
+/// if requested. Returns the Disconnected notification to send if the connection was
+/// closed locally, or the error that ended it.
+async fn connect_and_process<R: ProcessorOutputReceiver + Send>(
+    connection: &mut ConnectionReceiver,
+    mut data: commands::Connect,
+    receiver: &mut R,
+) -> io::Result<DaemonNotification> {
+    let local_disconnect = || DaemonNotification::Disconnected {
+        reason: DisconnectReason::LocalDisconnect,
+        error: None,
+        exit_code: None,
+    };
+
+    let (mut transport, uri) = if let Some(replay) = data.replay.take() {
+        (BoxedTransport::replay(replay, 4096).await?, None)
     } else {
         let uri = Uri::from_string(&data.uri)?;
-        match BoxedTransport::connect_uri(uri, 4096).await {
-            Ok(transport) => transport,
+        let options = connect_options(&connection.state, receiver);
+        match BoxedTransport::connect_uri(uri.clone(), 4096, options).await {
+            Ok(transport) => (transport, Some(uri)),
             Err(err) => {
-                receiver.begin_chunk()?;
-                receiver.system(SystemMessage::ConnectionStatus(format!(
-                    "Failed to connect: {err}\n",
-                )))?;
-                receiver.end_chunk()?;
-                receiver.notification(DaemonNotification::Disconnected)?;
-                return Ok(());
+                connection_status(receiver, format!("Failed to connect: {err}\n",))?;
+                return Err(err);
             }
         }
     };
 
-    register_processors(state.clone(), &mut connection, processor_receiver);
+    loop {
+        receiver.notification(DaemonNotification::Connected)?;
 
-    receiver.notification(DaemonNotification::Connected)?;
-
-    let result = process_connection(transport, connection, &mut receiver).await;
-    if let Err(error) = result {
-        match error.kind() {
-            io::ErrorKind::UnexpectedEof
-            | io::ErrorKind::TimedOut
-            | io::ErrorKind::ConnectionReset => {
-                let message = if error.kind() == io::ErrorKind::UnexpectedEof {
-                    "Disconnected.".to_string()
-                } else {
-                    format!("Disconnected: {error}")
-                };
-                receiver.begin_chunk()?;
-                receiver.system(SystemMessage::ConnectionStatus(message))?;
-                receiver.end_chunk()?;
-            }
-            _ => {
-                return Err(error);
+        let error = match process_connection(transport, connection, receiver).await {
+            Ok(()) => return Ok(local_disconnect()),
+            Err(error) => error,
+        };
+
+        let message = if error.kind() == io::ErrorKind::UnexpectedEof {
+            "Disconnected.".to_string()
+        } else {
+            format!("Disconnected: {error}")
+        };
+        connection_status(receiver, message)?;
+
+        let (Some(uri), Some(policy)) = (&uri, connection.state.reconnect_policy()) else {
+            return Err(error);
+        };
+
+        reset_session_state(connection, receiver)?;
+
+        *connection.state.reconnecting.lock().unwrap() = true;
+        let reconnected = reconnect(uri, &policy, connection, receiver).await;
+        *connection.state.reconnecting.lock().unwrap() = false;
+
+        transport = match reconnected? {
+            Reconnected::Connected(transport) => transport,
+            Reconnected::Cancelled => return Ok(local_disconnect()),
+            Reconnected::GaveUp(error) => return Err(error),
+        };
+    }
+}
+
+fn connection_status<R: ProcessorOutputReceiver>(
+    receiver: &mut R,
+    message: String,
+) -> io::Result<()> {
+    receiver.begin_chunk()?;
+    receiver.system(SystemMessage::ConnectionStatus(message))?;
+    receiver.end_chunk()
+}
+
+fn connect_options<'a, R: ProcessorOutputReceiver + Send>(
+    state: &ConnectionState,
+    receiver: &'a mut R,
+) -> ConnectOptions<'a> {
+    ConnectOptions {
+        on_status: Box::new(|status| {
+            // Progress updates are purely informational; if they fail to send,
+            // whatever is wrong with the receiver will surface soon enough
+            match status {
+                ConnectStatus::Certificate {
+                    fingerprint,
+                    verified,
+                } => receiver.notification(DaemonNotification::TlsCertificate {
+                    fingerprint,
+                    verified,
+                }),
+                status => connection_status(receiver, status.to_string()),
             }
-        }
+            .ok();
+        }),
+        ..Default::default()
     }
+    .with_timeout(state.connect_timeout())
+}
 
-    receiver.notification(DaemonNotification::Disconnected)?;
-    state.lock().unwrap().connections.drop(connection_id);
+/// Reset any state that was negotiated with the server for the previous session
+fn reset_session_state<R: ProcessorOutputReceiver>(
+    connection: &ConnectionReceiver,
+    receiver: &mut R,
+) -> io::Result<()> {
+    connection
+        .state
+        .processor
+        .lock()
+        .unwrap()
+        .set_mxp_enabled(false);
+
+    connection.state.negotiated_encoding.lock().unwrap().take();
+
+    let was_server_echo_enabled = std::mem::replace(
+        &mut connection
+            .state
+            .ui_state
+            .lock()
+            .unwrap()
+            .is_server_echo_enabled,
+        false,
+    );
+    if was_server_echo_enabled {
+        receiver.notification(DaemonNotification::PasswordMode { enabled: false })?;
+    }
 
     Ok(())
 }
 
+enum Reconnected {
+    Connected(BoxedTransport),
+
+    /// We were asked to disconnect while waiting to reconnect
+    Cancelled,
+
+    /// We ran out of attempts; contains the error from the last one
+    GaveUp(io::Error),
+}
+
+/// Attempt to reconnect to `uri` with exponential backoff, according to `policy`
+async fn reconnect<R: ProcessorOutputReceiver + Send>(
+    uri: &Uri,
+    policy: &ReconnectPolicy,
+    connection: &mut ConnectionReceiver,
+    receiver: &mut R,
+) -> io::Result<Reconnected> {
+    let mut attempt = 0;
+    let mut last_error = None;
+    loop {
+        attempt += 1;
+        if matches!(policy.max_attempts, Some(max) if attempt > max) {
+            connection_status(
+                receiver,
+                format!("Giving up after {} attempts.", attempt - 1),
+            )?;
+            return Ok(Reconnected::GaveUp(last_error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotConnected, "No attempts allowed")
+            })));
+        }
+
+        let delay = policy.delay_for_attempt(attempt);
+        receiver.notification(DaemonNotification::Reconnecting {
+            attempt,
+            delay: delay.as_millis() as u64,
+        })?;
+        connection_status(
+            receiver,
+            format!(
+                "Reconnecting in {:.1}s (attempt {attempt})...",
+                delay.as_secs_f32()
+            ),
+        )?;
+
+        let sleep = time::sleep(delay);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                outgoing = connection.outbox.recv() => match outgoing {
+                    Some(Outgoing::Disconnect) | None => return Ok(Reconnected::Cancelled),
+
+                    // Sent to the new session once we've reconnected
+                    Some(Outgoing::WindowSize { width, height }) => {
+                        *connection.state.window_size.lock().unwrap() = Some((width, height));
+                    }
+
+                    // NOTE: The environment is stored in the connection state by
+                    // configure_connection, and will be sent to the new session
+                    Some(Outgoing::Environment(_)) => {}
+
+                    // Clients are told these can't be sent while reconnecting, so
+                    // these could only have been enqueued just as we disconnected
+                    Some(Outgoing::Text(_) | Outgoing::Event(_)) => {
+                        log::trace!(target: "transport", "Dropped outgoing message while reconnecting");
+                    }
+                },
+            }
+        }
+
+        let options = connect_options(&connection.state, receiver);
+        match BoxedTransport::connect_uri(uri.clone(), 4096, options).await {
+            Ok(transport) => return Ok(Reconnected::Connected(transport)),
+            Err(err) => {
+                connection_status(receiver, format!("Failed to reconnect: {err}"))?;
+                last_error = Some(err);
+            }
+        }
+    }
+}
+
 pub fn handle_received_text<R: ProcessorOutputReceiver>(
     receiver: &mut R,
     processor: &Mutex<TextProcessor>,
@@ -220,11 +579,14 @@ pub fn handle_sent_text<R: ProcessorOutputReceiver>(
     receiver: &mut R,
     processor: &Mutex<TextProcessor>,
     text: String,
+    echo: bool,
 ) -> io::Result<()> {
     receiver.begin_chunk()?;
 
     processor.lock().unwrap().consume_pending_line()?;
-    receiver.system(SystemMessage::LocalSend(text))?;
+    if echo {
+        receiver.system(SystemMessage::LocalSend(text))?;
+    }
 
     receiver.end_chunk()?;
 
@@ -241,3 +603,89 @@ pub fn handle_end_of_prompt<R: ProcessorOutputReceiver>(
 
     receiver.end_chunk()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app::{connections::Connections, processing::ansi::Ansi},
+        net::encoding::TextEncoding,
+    };
+
+    use super::*;
+
+    #[test]
+    fn activity_deadline_test() {
+        let mut activity = Activity::default();
+        let keepalive = KeepalivePolicy {
+            interval_ms: 60_000,
+            command: None,
+        };
+        let idle = Duration::from_secs(30);
+
+        assert_eq!(activity.next_deadline(None, None), None);
+        assert_eq!(
+            activity.next_deadline(Some(&keepalive), Some(idle)),
+            Some(activity.last_received + idle)
+        );
+        assert!(!activity.is_keepalive_due(&keepalive));
+        assert_eq!(activity.take_idle(idle), None);
+
+        activity.last_received -= Duration::from_secs(42);
+        assert_eq!(activity.take_idle(idle), Some(42));
+
+        // Only once per idle period:
+        assert_eq!(activity.take_idle(idle), None);
+        assert_eq!(
+            activity.next_deadline(Some(&keepalive), Some(idle)),
+            Some(activity.last_sent + Duration::from_secs(60))
+        );
+
+        activity.on_received(TransportEvent::EndOfPrompt);
+        assert!(!activity.idle_notified);
+    }
+
+    struct NullReceiver;
+
+    impl ProcessorOutputReceiver for NullReceiver {
+        fn window_size_source(&self) -> Option<WindowSizeSource> {
+            None
+        }
+
+        fn new_line(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn finish_line(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn clear_partial_line(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn text(&mut self, _text: Ansi) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn system(&mut self, _text: SystemMessage) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn notification(&mut self, _notification: DaemonNotification) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reset_negotiated_encoding_test() -> io::Result<()> {
+        let connection = Connections::default().create();
+        *connection.state.encoding.lock().unwrap() = TextEncoding::Cp437;
+        *connection.state.negotiated_encoding.lock().unwrap() = Some(TextEncoding::Utf8);
+        assert_eq!(connection.state.encoding(), TextEncoding::Utf8);
+
+        reset_session_state(&connection, &mut NullReceiver)?;
+        assert_eq!(connection.state.encoding(), TextEncoding::Cp437);
+
+        Ok(())
+    }
+}
@@ -4,11 +4,15 @@ pub mod configure_connection;
 pub mod connect;
 pub mod disconnect;
 pub mod get_history;
+pub mod query_mssp;
 pub mod register_alias;
 pub mod register_prompt;
 pub mod register_trigger;
 pub mod scroll_history;
 pub mod send;
+pub mod send_event;
 pub mod set_active_prompt_group;
+pub mod set_matcher_group_enabled;
 pub mod set_prompt_content;
+pub mod unregister_matcher;
 pub mod window_size;
@@ -0,0 +1,65 @@
+use std::{io, time::Duration};
+
+use tokio::time;
+
+use crate::{
+    daemon::{channel::Channel, responses::DaemonResponse},
+    net::Uri,
+    transport::{
+        telnet::options::mssp, BoxedTransport, ConnectOptions, Transport, TransportEvent,
+        TransportEventValue, TransportNotification,
+    },
+};
+
+/// Servers generally send MSSP right away if they support it, so there's no
+/// sense waiting around very long
+const MSSP_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub async fn handle(channel: Channel, uri: String) {
+    let transport = match Uri::from_string(&uri) {
+        Ok(uri) => BoxedTransport::connect_uri(uri, 4096, ConnectOptions::default()).await,
+        Err(err) => Err(err),
+    };
+    let mut transport = match transport {
+        Ok(transport) => transport,
+        Err(err) => {
+            channel.respond(DaemonResponse::ErrorResult {
+                error: format!("Failed to connect: {err}"),
+            });
+            return;
+        }
+    };
+
+    let result = time::timeout(MSSP_TIMEOUT, read_mssp(&mut transport)).await;
+
+    // We're done with the connection either way
+    transport.close().await.ok();
+
+    match result {
+        Ok(Ok(status)) => channel.respond(DaemonResponse::MsspResult { status }),
+
+        // If the server hung up or took too long, it just doesn't support MSSP
+        Err(_) => channel.respond(DaemonResponse::MsspResult { status: None }),
+        Ok(Err(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            channel.respond(DaemonResponse::MsspResult { status: None })
+        }
+
+        Ok(Err(err)) => channel.respond(DaemonResponse::ErrorResult {
+            error: err.to_string(),
+        }),
+    };
+}
+
+async fn read_mssp<T: Transport>(transport: &mut T) -> io::Result<Option<TransportEventValue>> {
+    transport
+        .notify(TransportNotification::WindowSizeUnavailable)
+        .await?;
+
+    loop {
+        if let TransportEvent::Event(event) = transport.read().await? {
+            if event.ns() == mssp::NAMESPACE && event.name() == mssp::STATUS_EVENT {
+                return Ok(event.into_payload());
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use std::{io, sync::Arc};
+use std::{io, sync::Arc, time::Duration};
 
-use tokio::sync::Mutex;
+use tokio::{sync::Mutex, time};
 
 use crate::{
     app::{
@@ -10,8 +10,8 @@ use crate::{
         Id, LockableState,
     },
     daemon::{
-        channel::{Channel, ConnectionChannel},
-        commands::AliasReplacement,
+        channel::{Channel, ConnectionChannel, ConnectionNotifier},
+        commands::{AliasReplacement, MatcherRegistration},
         requests::ServerRequest,
         responses::{ClientResponse, DaemonResponse},
     },
@@ -20,13 +20,15 @@ use crate::{
 async fn register_handler_matcher(
     channel: ConnectionChannel,
     processor_ref: Arc<Mutex<SendTextProcessor>>,
+    registration: MatcherRegistration,
     matcher: Matcher,
     handler_id: Id,
-) {
-    processor_ref
-        .lock()
-        .await
-        .register_matcher(matcher, move |context| {
+) -> Id {
+    processor_ref.lock().await.register_matcher(
+        registration.matcher_id,
+        registration.group,
+        matcher,
+        move |context| {
             let mut receiver = channel.clone();
             async move {
                 let response = receiver
@@ -52,24 +54,28 @@ async fn register_handler_matcher(
                     )),
                 }
             }
-        });
+        },
+    )
 }
 
 async fn register_formatter_matcher(
     processor_ref: Arc<Mutex<SendTextProcessor>>,
+    registration: MatcherRegistration,
     matcher: Matcher,
     formatter: FormatterSpec,
-) -> Result<(), MatcherCompileError> {
+) -> Result<Id, MatcherCompileError> {
     let formatter: Formatter = formatter.try_into()?;
-    processor_ref
-        .lock()
-        .await
-        .register_matcher(matcher, move |context| {
+    let matcher_id = processor_ref.lock().await.register_matcher(
+        registration.matcher_id,
+        registration.group,
+        matcher,
+        move |context| {
             let replacement = formatter.format(context);
             async move { Ok(ProcessResult::ReplaceWith(replacement)) }
-        });
+        },
+    );
 
-    Ok(())
+    Ok(matcher_id)
 }
 
 pub async fn handle(
@@ -78,6 +84,7 @@ pub async fn handle(
     connection_id: Id,
     matcher: MatcherSpec,
     replacement: AliasReplacement,
+    registration: MatcherRegistration,
 ) {
     let processor_ref = if let Some(reference) = state
         .lock()
@@ -101,6 +108,16 @@ pub async fn handle(
         }
     };
 
+    if compiled.is_multi_line() {
+        channel.respond(DaemonResponse::ErrorResult {
+            error: format!(
+                "Invalid matcher ({:?}); aliases must NOT be multi-line",
+                compiled
+            ),
+        });
+        return;
+    }
+
     if compiled.options.consume {
         channel.respond(DaemonResponse::ErrorResult {
             error: format!("Invalid matcher ({:?}); must NOT be `consume`", compiled),
@@ -108,29 +125,51 @@ pub async fn handle(
         return;
     }
 
-    match replacement {
+    let expires_after_ms = compiled.options.expires_after_ms;
+    let mut expiry_notifier = channel.for_connection(connection_id);
+    let matcher_id = match replacement {
         AliasReplacement::Handler { handler_id } => {
             register_handler_matcher(
                 channel.for_connection(connection_id),
-                processor_ref,
+                processor_ref.clone(),
+                registration,
                 compiled,
                 handler_id,
             )
-            .await;
+            .await
         }
 
         AliasReplacement::Simple {
             replacement_pattern: formatter,
         } => {
-            let result = register_formatter_matcher(processor_ref, compiled, formatter).await;
-            if let Err(e) = result {
-                channel.respond(DaemonResponse::ErrorResult {
-                    error: format!("{:?}", e),
-                });
-                return;
+            let result = register_formatter_matcher(
+                processor_ref.clone(),
+                registration,
+                compiled,
+                formatter,
+            )
+            .await;
+            match result {
+                Ok(matcher_id) => matcher_id,
+                Err(e) => {
+                    channel.respond(DaemonResponse::ErrorResult {
+                        error: format!("{:?}", e),
+                    });
+                    return;
+                }
             }
         }
     };
 
-    channel.respond(DaemonResponse::OkResult);
+    channel.respond(DaemonResponse::MatcherRegistered { matcher_id });
+
+    // NOTE: As with triggers, remove and notify promptly instead of waiting
+    // for the next send
+    if let Some(expires_after_ms) = expires_after_ms {
+        time::sleep(Duration::from_millis(expires_after_ms)).await;
+        let expired = processor_ref.lock().await.remove_expired_matchers();
+        for notification in expired {
+            expiry_notifier.notify(notification);
+        }
+    }
 }
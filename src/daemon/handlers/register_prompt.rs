@@ -3,11 +3,11 @@ use crate::{
         matchers::{Matcher, MatcherSpec},
         processing::{
             ansi::Ansi,
-            text::{MatcherId, MatcherMode},
+            text::{MatcherMode, MatcherSource},
         },
         Id, LockableState,
     },
-    daemon::{channel::Channel, responses::DaemonResponse},
+    daemon::{channel::Channel, commands::MatcherRegistration, responses::DaemonResponse},
 };
 
 use super::set_prompt_content;
@@ -19,6 +19,7 @@ pub fn try_handle(
     matcher: MatcherSpec,
     group_id: Id,
     prompt_index: usize,
+    registration: MatcherRegistration,
 ) -> DaemonResponse {
     let processor_ref = if let Some(reference) = state
         .lock()
@@ -40,17 +41,28 @@ pub fn try_handle(
         }
     };
 
+    if compiled.is_multi_line() {
+        return DaemonResponse::ErrorResult {
+            error: format!(
+                "Invalid matcher ({:?}); prompts must NOT be multi-line",
+                compiled
+            ),
+        };
+    }
+
     // Prompts should always consume:
     compiled.options.consume = true;
 
-    let id = MatcherId::Prompt {
+    let source = MatcherSource::Prompt {
         group: group_id,
         index: prompt_index,
     };
 
     let mut receiver = channel.map(|channel| channel.for_connection(connection_id));
-    processor_ref.lock().unwrap().register_matcher(
-        id,
+    let matcher_id = processor_ref.lock().unwrap().register_matcher(
+        registration.matcher_id,
+        registration.group,
+        source,
         compiled,
         MatcherMode::PartialLine,
         move |mut context| {
@@ -67,7 +79,7 @@ pub fn try_handle(
         },
     );
 
-    return DaemonResponse::OkResult;
+    return DaemonResponse::MatcherRegistered { matcher_id };
 }
 
 pub async fn handle(
@@ -77,6 +89,7 @@ pub async fn handle(
     matcher: MatcherSpec,
     group_id: Id,
     prompt_index: usize,
+    registration: MatcherRegistration,
 ) {
     let response = try_handle(
         Some(&channel),
@@ -85,6 +98,7 @@ pub async fn handle(
         matcher,
         group_id,
         prompt_index,
+        registration,
     );
     channel.respond(response);
 }
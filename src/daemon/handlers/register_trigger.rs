@@ -1,11 +1,16 @@
+use std::time::Duration;
+
+use tokio::time;
+
 use crate::{
     app::{
-        matchers::MatcherSpec,
-        processing::text::{MatcherId, MatcherMode},
+        matchers::{Matcher, MatcherSpec},
+        processing::text::{MatcherMode, MatcherSource},
         Id, LockableState,
     },
     daemon::{
         channel::{Channel, ConnectionNotifier},
+        commands::MatcherRegistration,
         notifications::DaemonNotification,
         responses::DaemonResponse,
     },
@@ -17,6 +22,7 @@ pub async fn handle(
     connection_id: Id,
     matcher: MatcherSpec,
     handler_id: Id,
+    registration: MatcherRegistration,
 ) {
     let processor_ref = if let Some(reference) = state
         .lock()
@@ -30,7 +36,7 @@ pub async fn handle(
         return;
     };
 
-    let compiled = match matcher.try_into() {
+    let compiled: Matcher = match matcher.try_into() {
         Ok(compiled) => compiled,
         Err(e) => {
             channel.respond(DaemonResponse::ErrorResult {
@@ -40,9 +46,13 @@ pub async fn handle(
         }
     };
 
+    let expires_after_ms = compiled.options.expires_after_ms;
     let mut receiver = channel.for_connection(connection_id);
-    processor_ref.lock().unwrap().register_matcher(
-        MatcherId::Handler(handler_id),
+    let mut expiry_notifier = channel.for_connection(connection_id);
+    let matcher_id = processor_ref.lock().unwrap().register_matcher(
+        registration.matcher_id,
+        registration.group,
+        MatcherSource::Trigger { handler_id },
         compiled,
         MatcherMode::FullLine,
         move |context| {
@@ -54,5 +64,15 @@ pub async fn handle(
         },
     );
 
-    channel.respond(DaemonResponse::OkResult);
+    channel.respond(DaemonResponse::MatcherRegistered { matcher_id });
+
+    // NOTE: Expired matchers are skipped as soon as they expire, but we don't
+    // want to wait for more output to actually remove them and notify
+    if let Some(expires_after_ms) = expires_after_ms {
+        time::sleep(Duration::from_millis(expires_after_ms)).await;
+        let expired = processor_ref.lock().unwrap().remove_expired_matchers();
+        for notification in expired {
+            expiry_notifier.notify(notification);
+        }
+    }
 }
@@ -2,7 +2,10 @@ use std::io;
 
 use crate::{
     app::{connections::Outgoing, Id, LockableState},
-    daemon::{channel::Channel, responses::DaemonResponse},
+    daemon::{
+        channel::{Channel, ConnectionNotifier},
+        responses::DaemonResponse,
+    },
 };
 
 async fn process_aliases(
@@ -23,12 +26,27 @@ async fn process_aliases(
         return (channel, Ok(Some(text)));
     };
 
-    let processor = processor_ref.lock().await;
+    let mut processor = processor_ref.lock().await;
     let result = processor.process(text).await;
 
+    let mut notifier = channel.for_connection(connection_id);
+    for notification in processor.remove_expired_matchers() {
+        notifier.notify(notification);
+    }
+
     (channel, result)
 }
 
+/// If we're waiting to reconnect, there's nowhere to send anything
+pub fn is_reconnecting(state: &mut LockableState, connection_id: Id) -> bool {
+    state
+        .lock()
+        .unwrap()
+        .connections
+        .get_state(connection_id)
+        .is_some_and(|connection| connection.is_reconnecting())
+}
+
 pub async fn handle(
     channel: Channel,
     mut state: LockableState,
@@ -36,8 +54,19 @@ pub async fn handle(
     text: String,
     persist: bool,
 ) {
-    let (channel, text_result) =
-        process_aliases(channel, state.clone(), connection_id, text.clone()).await;
+    // If the server is echoing (IE: we're probably entering a password) we should
+    // avoid leaking the text to aliases or storing it anywhere
+    let is_password = state
+        .lock()
+        .unwrap()
+        .connections
+        .get_state(connection_id)
+        .is_some_and(|connection| connection.is_server_echo_enabled());
+    let (channel, text_result) = if is_password {
+        (channel, Ok(Some(text.clone())))
+    } else {
+        process_aliases(channel, state.clone(), connection_id, text.clone()).await
+    };
     let to_send = match text_result {
         Ok(Some(text)) => text,
 
@@ -50,16 +79,18 @@ pub async fn handle(
         }
     };
 
-    // Enqueue the processed text to be sent
+    // Enqueue the processed text to be sent (unless there's nowhere to send it)
     let outbox = state.lock().unwrap().connections.get_outbox(connection_id);
-    let sent = if let Some(outbox) = outbox {
+    let sent = if is_reconnecting(&mut state, connection_id) {
+        false
+    } else if let Some(outbox) = outbox {
         outbox.send(Outgoing::Text(to_send)).await.is_ok()
     } else {
         false
     };
 
     // After sending successfully, process the input text (unless disabled)
-    if persist {
+    if persist && !is_password {
         if let Some(connection) = state.lock().unwrap().connections.get_state(connection_id) {
             // Add to send history
             let mut sent = connection.sent.lock().unwrap();
@@ -0,0 +1,23 @@
+use crate::{
+    app::{connections::Outgoing, Id, LockableState},
+    daemon::{channel::Channel, handlers::send::is_reconnecting, responses::DaemonResponse},
+    transport::EventData,
+};
+
+pub async fn handle(
+    channel: Channel,
+    mut state: LockableState,
+    connection_id: Id,
+    event: EventData,
+) {
+    let outbox = state.lock().unwrap().connections.get_outbox(connection_id);
+    let sent = if is_reconnecting(&mut state, connection_id) {
+        false
+    } else if let Some(outbox) = outbox {
+        outbox.send(Outgoing::Event(event)).await.is_ok()
+    } else {
+        false
+    };
+
+    channel.respond(DaemonResponse::SendResult { sent });
+}
@@ -0,0 +1,33 @@
+use crate::{
+    app::{Id, LockableState},
+    daemon::{channel::Channel, responses::DaemonResponse},
+};
+
+pub async fn handle(
+    channel: Channel,
+    mut state: LockableState,
+    connection_id: Id,
+    group: String,
+    enabled: bool,
+) {
+    let conn_state =
+        if let Some(reference) = state.lock().unwrap().connections.get_state(connection_id) {
+            reference
+        } else {
+            channel.respond(DaemonResponse::OkResult);
+            return;
+        };
+
+    conn_state
+        .processor
+        .lock()
+        .unwrap()
+        .set_matcher_group_enabled(&group, enabled);
+    conn_state
+        .send_processor
+        .lock()
+        .await
+        .set_matcher_group_enabled(&group, enabled);
+
+    channel.respond(DaemonResponse::OkResult);
+}
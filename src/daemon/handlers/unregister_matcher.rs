@@ -0,0 +1,48 @@
+use crate::{
+    app::{Id, LockableState},
+    daemon::{channel::Channel, notifications::MatcherKind, responses::DaemonResponse},
+};
+
+pub async fn handle(
+    channel: Channel,
+    mut state: LockableState,
+    connection_id: Id,
+    kind: MatcherKind,
+    matcher_id: Id,
+) {
+    let conn_state =
+        if let Some(reference) = state.lock().unwrap().connections.get_state(connection_id) {
+            reference
+        } else {
+            channel.respond(DaemonResponse::OkResult);
+            return;
+        };
+
+    let removed = match kind {
+        MatcherKind::Alias => conn_state
+            .send_processor
+            .lock()
+            .await
+            .unregister_matcher(matcher_id),
+
+        MatcherKind::Prompt => conn_state
+            .processor
+            .lock()
+            .unwrap()
+            .unregister_matcher(matcher_id, |source| source.is_prompt()),
+
+        MatcherKind::Trigger => conn_state
+            .processor
+            .lock()
+            .unwrap()
+            .unregister_matcher(matcher_id, |source| !source.is_prompt()),
+    };
+
+    if removed {
+        channel.respond(DaemonResponse::OkResult);
+    } else {
+        channel.respond(DaemonResponse::ErrorResult {
+            error: format!("No such {kind:?} matcher: {matcher_id}"),
+        });
+    }
+}
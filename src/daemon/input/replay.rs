@@ -1,4 +1,4 @@
-use std::{io, path::PathBuf};
+use std::{io, path::PathBuf, time::Duration};
 
 use tokio::sync::oneshot;
 
@@ -10,14 +10,18 @@ use crate::daemon::{
 
 pub struct DumpReplayRequestSource {
     path: PathBuf,
+    speed: Option<f64>,
+    seek: Duration,
     has_started: bool,
     to_await: Option<oneshot::Receiver<()>>,
 }
 
 impl DumpReplayRequestSource {
-    pub fn for_path(path: PathBuf) -> Self {
+    pub fn for_path(path: PathBuf, speed: Option<f64>, seek: Duration) -> Self {
         Self {
             path,
+            speed,
+            seek,
             has_started: false,
             to_await: None,
         }
@@ -45,6 +49,8 @@ impl DaemonRequestSource for DumpReplayRequestSource {
                 replay: Some(ReplayConfig {
                     path: self.path.clone(),
                     on_complete: tx,
+                    speed: self.speed,
+                    seek: self.seek,
                 }),
             }),
         }))
@@ -4,7 +4,7 @@ use std::{
 };
 
 pub mod channel;
-mod commands;
+pub(crate) mod commands;
 pub mod handlers;
 pub mod input;
 pub mod notifications;
@@ -20,6 +20,7 @@ use crate::app::{
 use self::{
     channel::{Channel, ChannelSource},
     commands::{ClientNotification, ClientRequest},
+    notifications::MatcherKind,
     protocol::{Request, RequestIdGenerator},
 };
 
@@ -29,7 +30,9 @@ where
 {
     tokio::spawn(async {
         if let Err(e) = handler.await {
-            panic!("ERR: {e}");
+            // NOTE: Handlers are responsible for cleaning up after themselves; we just
+            // don't want to take the whole daemon down with them
+            log::error!("Request handler failed: {e}");
         }
     });
 }
@@ -104,6 +107,10 @@ fn dispatch_request<TUI: ProcessorOutputReceiverFactory + 'static>(
             launch(handlers::connect::handle(ui, channel, state, data));
         }
 
+        ClientRequest::QueryMssp { uri } => {
+            tokio::spawn(handlers::query_mssp::handle(channel, uri));
+        }
+
         ClientRequest::ConfigureConnection {
             connection_id,
             config,
@@ -112,7 +119,7 @@ fn dispatch_request<TUI: ProcessorOutputReceiverFactory + 'static>(
                 channel,
                 state,
                 connection_id,
-                config,
+                *config,
             ));
         }
 
@@ -136,6 +143,18 @@ fn dispatch_request<TUI: ProcessorOutputReceiverFactory + 'static>(
             ));
         }
 
+        ClientRequest::SendEvent {
+            connection_id,
+            event,
+        } => {
+            tokio::spawn(handlers::send_event::handle(
+                channel,
+                state,
+                connection_id,
+                event,
+            ));
+        }
+
         ClientRequest::CompleteComposer {
             connection_id: connection,
             params,
@@ -163,6 +182,7 @@ fn dispatch_request<TUI: ProcessorOutputReceiverFactory + 'static>(
             connection_id,
             matcher,
             replacement,
+            registration,
         } => {
             tokio::spawn(handlers::register_alias::handle(
                 channel,
@@ -170,6 +190,7 @@ fn dispatch_request<TUI: ProcessorOutputReceiverFactory + 'static>(
                 connection_id,
                 matcher,
                 replacement,
+                registration,
             ));
         }
 
@@ -178,6 +199,7 @@ fn dispatch_request<TUI: ProcessorOutputReceiverFactory + 'static>(
             matcher,
             group_id,
             prompt_index,
+            registration,
         } => {
             tokio::spawn(handlers::register_prompt::handle(
                 channel,
@@ -186,6 +208,7 @@ fn dispatch_request<TUI: ProcessorOutputReceiverFactory + 'static>(
                 matcher,
                 group_id,
                 prompt_index,
+                registration,
             ));
         }
 
@@ -193,9 +216,68 @@ fn dispatch_request<TUI: ProcessorOutputReceiverFactory + 'static>(
             connection_id: connection,
             matcher,
             handler_id,
+            registration,
         } => {
             tokio::spawn(handlers::register_trigger::handle(
-                channel, state, connection, matcher, handler_id,
+                channel,
+                state,
+                connection,
+                matcher,
+                handler_id,
+                registration,
+            ));
+        }
+
+        ClientRequest::SetMatcherGroupEnabled {
+            connection_id,
+            group,
+            enabled,
+        } => {
+            tokio::spawn(handlers::set_matcher_group_enabled::handle(
+                channel,
+                state,
+                connection_id,
+                group,
+                enabled,
+            ));
+        }
+
+        ClientRequest::UnregisterAlias {
+            connection_id,
+            matcher_id,
+        } => {
+            tokio::spawn(handlers::unregister_matcher::handle(
+                channel,
+                state,
+                connection_id,
+                MatcherKind::Alias,
+                matcher_id,
+            ));
+        }
+
+        ClientRequest::UnregisterPrompt {
+            connection_id,
+            matcher_id,
+        } => {
+            tokio::spawn(handlers::unregister_matcher::handle(
+                channel,
+                state,
+                connection_id,
+                MatcherKind::Prompt,
+                matcher_id,
+            ));
+        }
+
+        ClientRequest::UnregisterTrigger {
+            connection_id,
+            matcher_id,
+        } => {
+            tokio::spawn(handlers::unregister_matcher::handle(
+                channel,
+                state,
+                connection_id,
+                MatcherKind::Trigger,
+                matcher_id,
             ));
         }
 
@@ -1,12 +1,12 @@
 pub mod external_ui;
 
-use std::{collections::HashMap, ops::Range};
+use std::{collections::HashMap, io, ops::Range};
 
 use serde::Serialize;
 
 use crate::{
     app::{processing::ansi::Ansi, Id},
-    transport::EventData,
+    transport::{ConnectError, EventData, ProcessExited},
 };
 
 use self::external_ui::ExternalUINotification;
@@ -41,15 +41,110 @@ impl MatchContext {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum MatcherKind {
+    Alias,
+    Prompt,
+    Trigger,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum DisconnectReason {
+    /// The server closed the connection
+    RemoteClosed,
+
+    /// The connection was closed at the client's request
+    LocalDisconnect,
+
+    /// The server's host name could not be resolved
+    Dns,
+
+    /// The TLS handshake with the server failed
+    Tls,
+
+    Timeout,
+    Io,
+
+    /// The local process (for `exec:` uris) exited
+    ProcessExited,
+}
+
+impl DisconnectReason {
+    pub fn from_error(error: &io::Error) -> Self {
+        if ProcessExited::find(error).is_some() {
+            return DisconnectReason::ProcessExited;
+        }
+
+        match ConnectError::find(error) {
+            Some(ConnectError::Dns(_)) => return DisconnectReason::Dns,
+            Some(ConnectError::Tls(_) | ConnectError::FingerprintMismatch { .. }) => {
+                return DisconnectReason::Tls
+            }
+            None => {}
+        }
+
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => DisconnectReason::RemoteClosed,
+            io::ErrorKind::TimedOut => DisconnectReason::Timeout,
+            _ => DisconnectReason::Io,
+        }
+    }
+}
+
+impl DaemonNotification {
+    pub fn disconnected_by(error: &io::Error) -> Self {
+        let reason = DisconnectReason::from_error(error);
+        DaemonNotification::Disconnected {
+            reason,
+            error: if reason == DisconnectReason::RemoteClosed {
+                None
+            } else {
+                Some(error.to_string())
+            },
+            exit_code: ProcessExited::find(error).and_then(|exited| exited.0.code()),
+        }
+    }
+}
+
 #[derive(Serialize)]
 #[serde(tag = "type")]
 pub enum DaemonNotification {
     Connected,
-    Disconnected,
+
+    /// The connection has ended, and the connection_id is no longer valid. If it ended
+    /// due to an error, its description will be in `error`. If it was a local process
+    /// that exited normally, its exit code will be in `exit_code`.
+    Disconnected {
+        reason: DisconnectReason,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+    },
+
+    /// The connection was lost, and we will attempt to reconnect (for the `attempt`th
+    /// time) after `delay` milliseconds. `Connected` will be sent again on success.
+    Reconnecting {
+        attempt: u32,
+        delay: u64,
+    },
+
+    /// Nothing has been received from the server for `seconds` (see `idle_notify_ms`).
+    /// This is sent once per idle period.
+    Idle {
+        seconds: u64,
+    },
     TriggerMatched {
         handler_id: Id,
         context: MatchContext,
     },
+
+    /// The matcher was removed after firing `max_fires` times, or after
+    /// `expires_after_ms`. Matcher ids are only unique per `kind`.
+    MatcherExpired {
+        kind: MatcherKind,
+        matcher_id: Id,
+    },
     PromptUpdated {
         group_id: Id,
         index: usize,
@@ -58,6 +153,22 @@ pub enum DaemonNotification {
     ActivePromptGroupChanged {
         group_id: Id,
     },
+
+    /// Sent after the TLS handshake of a secure connection. `fingerprint` is the SHA-256
+    /// fingerprint of the server's certificate; `verified` is false if certificate
+    /// verification was disabled via the `insecure` uri param. Clients may store the
+    /// fingerprint and provide it later via the `fingerprint` uri param to detect changes.
+    TlsCertificate {
+        fingerprint: String,
+        verified: bool,
+    },
+
+    /// While `enabled`, the server has taken over echoing input (usually to prompt for a
+    /// password); clients should mask their composer input. Input sent in this mode will
+    /// not be echoed locally, nor stored in history.
+    PasswordMode {
+        enabled: bool,
+    },
     ExternalUI {
         data: ExternalUINotification,
     },
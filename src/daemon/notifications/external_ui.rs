@@ -1,5 +1,34 @@
 use serde::Serialize;
 
+/// What should happen when a [TextLink] is clicked
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum TextLinkAction {
+    /// Send one of `commands` to the server. If there are multiple, clients
+    /// should offer them as a menu (using the corresponding `hint`, if any).
+    /// If `prompt` is set, clients should let the user edit the command before
+    /// sending it.
+    Send {
+        commands: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hint: Option<String>,
+        prompt: bool,
+    },
+
+    Url {
+        href: String,
+    },
+}
+
+/// A clickable span of text. `start` and `end` are byte offsets into the
+/// `plain` text of the notification.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TextLink {
+    pub start: usize,
+    pub end: usize,
+    pub action: TextLinkAction,
+}
+
 #[derive(Serialize)]
 #[serde(tag = "type")]
 pub enum ExternalUINotification {
@@ -10,6 +39,8 @@ pub enum ExternalUINotification {
         ansi: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         plain: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        links: Vec<TextLink>,
     },
     ConnectionStatus {
         text: String,
@@ -78,7 +78,11 @@ mod tests {
 
     #[cfg(test)]
     mod serialization_tests {
-        use crate::daemon::notifications::external_ui::ExternalUINotification;
+        use std::io;
+
+        use crate::{
+            daemon::notifications::external_ui::ExternalUINotification, transport::ConnectError,
+        };
 
         use super::*;
 
@@ -92,6 +96,32 @@ mod tests {
             assert_eq!(s, r#"{"connection_id":42,"type":"Connected"}"#);
         }
 
+        #[test]
+        fn disconnected_test() {
+            let s = serde_json::to_string(&Notification::ForConnection {
+                connection_id: 42,
+                notification: DaemonNotification::disconnected_by(
+                    &io::ErrorKind::UnexpectedEof.into(),
+                ),
+            })
+            .unwrap();
+            assert_eq!(
+                s,
+                r#"{"connection_id":42,"type":"Disconnected","reason":"RemoteClosed"}"#
+            );
+
+            let error: io::Error = ConnectError::Dns(io::Error::other("no such host")).into();
+            let s = serde_json::to_string(&Notification::ForConnection {
+                connection_id: 42,
+                notification: DaemonNotification::disconnected_by(&error),
+            })
+            .unwrap();
+            assert_eq!(
+                s,
+                r#"{"connection_id":42,"type":"Disconnected","reason":"Dns","error":"Failed to resolve host: no such host"}"#
+            );
+        }
+
         #[test]
         fn external_ui_test() {
             let s = serde_json::to_string(&Notification::ForConnection {
@@ -100,6 +130,7 @@ mod tests {
                     data: ExternalUINotification::Text {
                         ansi: "Welcome!".to_string(),
                         plain: None,
+                        links: vec![],
                     },
                 },
             })
@@ -149,6 +180,38 @@ mod tests {
             );
         }
 
+        #[test]
+        fn send_event_test() {
+            let r: Request = serde_json::from_str(
+                r#"{
+                    "id": 9001,
+                    "type": "SendEvent",
+                    "connection_id": 42,
+                    "ns": "GMCP",
+                    "name": "Char.Items.Inv",
+                    "payload": {"limit": 10}
+                }"#,
+            )
+            .unwrap();
+
+            assert_matches!(
+                r,
+                Request::ForResponse {
+                    id: 9001,
+                    payload: ClientRequest::SendEvent {
+                        connection_id: 42,
+                        event,
+                    },
+                } => {
+                    assert_eq!(event.ns(), "GMCP");
+                    assert_eq!(
+                        serde_json::to_string(&event).unwrap(),
+                        r#"{"ns":"GMCP","name":"Char.Items.Inv","payload":{"limit":10}}"#
+                    );
+                }
+            );
+        }
+
         #[test]
         fn register_alias_test() {
             let r: Request = serde_json::from_str(
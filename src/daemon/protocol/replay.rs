@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use tokio::sync::oneshot;
 
@@ -6,4 +6,13 @@ use tokio::sync::oneshot;
 pub struct ReplayConfig {
     pub path: PathBuf,
     pub on_complete: oneshot::Sender<()>,
+
+    /// If provided, recorded events are replayed with their original timing,
+    /// sped up (or slowed down) by this factor. Otherwise, everything is replayed
+    /// as fast as possible.
+    pub speed: Option<f64>,
+
+    /// Events recorded before this point are replayed as fast as possible, and
+    /// timing (if enabled) resumes from there
+    pub seek: Duration,
 }
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::app::Id;
+use crate::{app::Id, transport::TransportEventValue};
 
 use super::protocol::cursors::HistoryCursor;
 
@@ -18,6 +18,12 @@ pub enum DaemonResponse {
     SendResult {
         sent: bool,
     },
+    MsspResult {
+        status: Option<TransportEventValue>,
+    },
+    MatcherRegistered {
+        matcher_id: Id,
+    },
 
     CompleteResult {
         words: Vec<String>,
@@ -67,8 +67,35 @@ async fn run(cli: Cli) -> io::Result<()> {
             run_with(cli, input, response).await
         }
 
-        Commands::ReplayDump { path, .. } => {
-            let input = DumpReplayRequestSource::for_path(path.clone());
+        Commands::ReplayDump {
+            path,
+            speed,
+            realtime,
+            seek,
+            ..
+        } => {
+            let speed = if *realtime {
+                speed.or(Some(1.0))
+            } else {
+                *speed
+            };
+            if speed.is_some_and(|speed| !speed.is_finite() || speed <= 0.0) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--speed must be a positive number",
+                ));
+            }
+            if !seek.is_finite() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--seek must be a number of seconds",
+                ));
+            }
+            let input = DumpReplayRequestSource::for_path(
+                path.clone(),
+                speed,
+                Duration::from_secs_f64(seek.max(0.0)),
+            );
             let response = io::stderr();
             run_with(cli, input, response).await
         }
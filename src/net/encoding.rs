@@ -0,0 +1,216 @@
+use std::fmt::Debug;
+
+use bytes::Bytes;
+use encoding_rs::{CoderResult, EncoderResult, Encoding, UTF_8};
+
+/// The upper half of Code Page 437, which encoding_rs doesn't support (since it's
+/// not part of the WHATWG Encoding Standard). The lower half is plain ASCII.
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+const CP437_LABELS: &[&str] = &["cp437", "ibm437", "ibm-437", "437", "cspc8codepage437"];
+
+/// Used in place of characters that can't be represented in the target encoding
+const UNMAPPABLE: u8 = b'?';
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Cp437,
+    Other(&'static Encoding),
+}
+
+impl Debug for TextEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TextEncoding({})", self.name())
+    }
+}
+
+impl TextEncoding {
+    /// Look up an encoding by its label (eg: "latin1" or "UTF-8"). Encodings that
+    /// aren't compatible with ASCII (like UTF-16) are not supported, since telnet
+    /// and ANSI sequences can't be represented with them.
+    pub fn for_label(label: &str) -> Option<Self> {
+        let label = label.trim();
+        if CP437_LABELS
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(label))
+        {
+            return Some(TextEncoding::Cp437);
+        }
+
+        match Encoding::for_label(label.as_bytes()) {
+            Some(encoding) if encoding == UTF_8 => Some(TextEncoding::Utf8),
+            Some(encoding) if encoding.is_ascii_compatible() => Some(TextEncoding::Other(encoding)),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => UTF_8.name(),
+            TextEncoding::Cp437 => "IBM437",
+            TextEncoding::Other(encoding) => encoding.name(),
+        }
+    }
+
+    pub fn decoder(&self) -> TextDecoder {
+        TextDecoder {
+            encoding: *self,
+            decoder: match self {
+                TextEncoding::Other(encoding) => Some(encoding.new_decoder_without_bom_handling()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Encode the given text for sending. Characters that can't be represented
+    /// in this encoding are replaced with `?`
+    pub fn encode(&self, text: &str) -> Bytes {
+        match self {
+            TextEncoding::Utf8 => Bytes::copy_from_slice(text.as_bytes()),
+            TextEncoding::Cp437 => text
+                .chars()
+                .map(|ch| {
+                    if ch.is_ascii() {
+                        ch as u8
+                    } else if let Some(index) = CP437_HIGH.iter().position(|c| *c == ch) {
+                        0x80 + index as u8
+                    } else {
+                        UNMAPPABLE
+                    }
+                })
+                .collect(),
+            TextEncoding::Other(encoding) => {
+                let mut encoder = encoding.new_encoder();
+                let mut encoded = Vec::with_capacity(text.len());
+                let mut remaining = text;
+                loop {
+                    let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(
+                        remaining,
+                        &mut encoded,
+                        true,
+                    );
+                    remaining = &remaining[read..];
+                    match result {
+                        EncoderResult::InputEmpty => break,
+                        EncoderResult::OutputFull => encoded.reserve(remaining.len().max(16)),
+                        EncoderResult::Unmappable(_) => encoded.push(UNMAPPABLE),
+                    }
+                }
+                encoded.into()
+            }
+        }
+    }
+}
+
+/// Transcodes a stream of bytes in some [TextEncoding] into UTF-8. Multi-byte
+/// sequences may be split across calls to [decode].
+pub struct TextDecoder {
+    encoding: TextEncoding,
+    decoder: Option<encoding_rs::Decoder>,
+}
+
+impl TextDecoder {
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    pub fn decode(&mut self, bytes: Bytes) -> Bytes {
+        match (&self.encoding, &mut self.decoder) {
+            (TextEncoding::Cp437, _) => {
+                if bytes.is_ascii() {
+                    return bytes;
+                }
+
+                let decoded: String = bytes
+                    .iter()
+                    .map(|b| {
+                        if b.is_ascii() {
+                            *b as char
+                        } else {
+                            CP437_HIGH[(b - 0x80) as usize]
+                        }
+                    })
+                    .collect();
+                decoded.into()
+            }
+
+            (TextEncoding::Other(_), Some(decoder)) => {
+                let mut decoded = String::with_capacity(bytes.len());
+                let mut remaining = &bytes[..];
+                loop {
+                    let (result, read, _) =
+                        decoder.decode_to_string(remaining, &mut decoded, false);
+                    remaining = &remaining[read..];
+                    match result {
+                        CoderResult::InputEmpty => break,
+                        CoderResult::OutputFull => decoded.reserve(remaining.len().max(16) * 3),
+                    }
+                }
+                decoded.into()
+            }
+
+            // The TextProcessor handles UTF-8 natively
+            _ => bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn for_label_test() {
+        assert_eq!(TextEncoding::for_label("utf8"), Some(TextEncoding::Utf8));
+        assert_eq!(TextEncoding::for_label("CP437"), Some(TextEncoding::Cp437));
+        assert_eq!(
+            TextEncoding::for_label("latin1").map(|e| e.name()),
+            Some("windows-1252")
+        );
+        assert_eq!(TextEncoding::for_label("utf-16le"), None);
+        assert_eq!(TextEncoding::for_label("klingon"), None);
+    }
+
+    #[test]
+    fn cp437_round_trip_test() {
+        let encoding = TextEncoding::Cp437;
+        let decoded = encoding
+            .decoder()
+            .decode(Bytes::from_static(b"\x1b[32m\xc9\xcd\xbb\x1b[m"));
+        assert_eq!(&decoded[..], "\x1b[32m╔═╗\x1b[m".as_bytes());
+
+        let encoded = encoding.encode("╔═╗ ☃");
+        assert_eq!(&encoded[..], b"\xc9\xcd\xbb ?");
+    }
+
+    #[test]
+    fn latin1_round_trip_test() {
+        let encoding = TextEncoding::for_label("latin1").unwrap();
+        let decoded = encoding.decoder().decode(Bytes::from_static(b"caf\xe9"));
+        assert_eq!(&decoded[..], "café".as_bytes());
+
+        let encoded = encoding.encode("café ☃");
+        assert_eq!(&encoded[..], b"caf\xe9 ?");
+    }
+
+    #[test]
+    fn split_multibyte_sequence_test() {
+        let encoding = TextEncoding::for_label("shift_jis").unwrap();
+        let mut decoder = encoding.decoder();
+        let first = decoder.decode(Bytes::from_static(b"\x82"));
+        let second = decoder.decode(Bytes::from_static(b"\xa0!"));
+        assert_eq!(&first[..], b"");
+        assert_eq!(&second[..], "あ!".as_bytes());
+    }
+}
@@ -0,0 +1,34 @@
+/// Options for running a local command as the "connection" (IE: `exec:dfrotz zork1.z5`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecOptions {
+    /// The command to run. This is passed to the system shell, so it may include
+    /// arguments, quoting, etc.
+    pub command: String,
+
+    /// If set, the command is run under a pseudo-terminal instead of plain pipes.
+    /// This is helpful for programs that buffer their output or otherwise behave
+    /// differently when not run interactively.
+    pub pty: bool,
+}
+
+impl ExecOptions {
+    /// Parse an `exec:<command>` or `exec+pty:<command>` uri
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        let (scheme, command) = uri.split_once(':')?;
+        let pty = match scheme {
+            "exec" => false,
+            "exec+pty" => true,
+            _ => return None,
+        };
+
+        let command = command.trim();
+        if command.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            command: command.to_string(),
+            pty,
+        })
+    }
+}
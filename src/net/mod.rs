@@ -1,5 +1,10 @@
+pub mod encoding;
+pub mod exec;
+pub mod proxy;
 pub mod readable;
+pub mod tls;
 mod uri;
+pub mod websocket;
 pub mod writable;
 
 pub use uri::Uri;
@@ -0,0 +1,129 @@
+use std::{io, path::PathBuf};
+
+use native_tls::{Certificate, Identity, TlsConnector};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+/// Options for TLS connections, generally provided as query params on the uri
+/// (IE: `ssl://host:port?ca=/path/to/ca.pem&insecure=1`)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsOptions {
+    /// Path to a PEM-encoded certificate to trust, in addition to the system roots
+    pub ca: Option<PathBuf>,
+
+    /// Path to a PEM-encoded client certificate to present to the server
+    pub cert: Option<PathBuf>,
+
+    /// Path to the PEM-encoded (PKCS #8) private key for `cert`
+    pub key: Option<PathBuf>,
+
+    /// If set, don't verify the server's certificate or hostname at all
+    pub insecure: bool,
+
+    /// If set, the server's certificate must have this SHA-256 fingerprint. This
+    /// is most useful combined with `insecure` for "trust on first use."
+    pub fingerprint: Option<String>,
+}
+
+impl TlsOptions {
+    pub fn set_param(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "ca" => self.ca = Some(value.into()),
+            "cert" => self.cert = Some(value.into()),
+            "key" => self.key = Some(value.into()),
+            "insecure" => self.insecure = !matches!(value, "" | "0" | "false"),
+            "fingerprint" => self.fingerprint = Some(value.to_string()),
+            _ => return false,
+        }
+        true
+    }
+
+    pub async fn connector(&self) -> io::Result<TlsConnector> {
+        let mut builder = TlsConnector::builder();
+
+        if let Some(ca) = &self.ca {
+            let pem = fs::read(ca).await?;
+            builder.add_root_certificate(Certificate::from_pem(&pem).map_err(invalid_input)?);
+        }
+
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => {
+                let cert = fs::read(cert).await?;
+                let key = fs::read(key).await?;
+                builder.identity(Identity::from_pkcs8(&cert, &key).map_err(invalid_input)?);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Client certificates require both `cert` and `key`",
+                ))
+            }
+        }
+
+        if self.insecure {
+            builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+
+        builder.build().map_err(io::Error::other)
+    }
+
+    /// Returns true if `fingerprint` is acceptable
+    pub fn accepts_fingerprint(&self, fingerprint: &str) -> bool {
+        match &self.fingerprint {
+            Some(expected) => normalize_fingerprint(expected) == normalize_fingerprint(fingerprint),
+            None => true,
+        }
+    }
+}
+
+fn invalid_input(err: native_tls::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    let fingerprint = fingerprint
+        .strip_prefix("sha256:")
+        .or_else(|| fingerprint.strip_prefix("SHA256:"))
+        .unwrap_or(fingerprint);
+    fingerprint
+        .chars()
+        .filter(|ch| *ch != ':')
+        .map(|ch| ch.to_ascii_uppercase())
+        .collect()
+}
+
+/// Format the SHA-256 fingerprint of a DER-encoded certificate like `AB:CD:...`
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_test() {
+        assert_eq!(
+            fingerprint(b"grayskull"),
+            "5A:A6:ED:33:95:47:77:82:22:89:3A:7B:E2:73:F5:F1:44:82:FF:82:86:92:DF:26:E4:2D:33:36:DC:53:51:1F"
+        );
+    }
+
+    #[test]
+    fn accepts_fingerprint_test() {
+        let options = TlsOptions {
+            fingerprint: Some("sha256:ab:cd:ef".to_string()),
+            ..Default::default()
+        };
+        assert!(options.accepts_fingerprint("AB:CD:EF"));
+        assert!(!options.accepts_fingerprint("AB:CD:00"));
+        assert!(TlsOptions::default().accepts_fingerprint("AB:CD:00"));
+    }
+}
@@ -1,17 +1,49 @@
 use std::io;
 
-use url::Url;
+use url::{form_urlencoded, Url};
 
-#[derive(Debug)]
+use super::{exec::ExecOptions, proxy::Proxy, tls::TlsOptions, websocket::WebSocketOptions};
+
+#[derive(Clone, Debug)]
 pub struct Uri {
     pub host: String,
     pub port: u16,
     pub tls: bool,
+    pub tls_options: TlsOptions,
+
+    /// A proxy to tunnel through. If not provided, the `KODACHI_PROXY` env var
+    /// will be checked when connecting.
+    pub proxy: Option<Proxy>,
+
+    /// If set, bytes are passed through as-is with no telnet processing or option
+    /// negotiation. Set for `raw://` uris, or with the `raw` param (IE: for TLS).
+    pub raw: bool,
+
+    /// Set for `ws://` and `wss://` uris
+    pub websocket: Option<WebSocketOptions>,
+
+    /// Set for `exec:` and `exec+pty:` uris, which run a local command instead
+    /// of connecting anywhere. `host` and `port` are unused.
+    pub exec: Option<ExecOptions>,
 }
 
 impl Uri {
     pub fn from_string(uri: &str) -> io::Result<Self> {
-        let url = if uri.find("://").is_none() {
+        if let Some(exec) = ExecOptions::from_uri(uri) {
+            return Ok(Self {
+                host: String::new(),
+                port: 0,
+                tls: false,
+                tls_options: TlsOptions::default(),
+                proxy: None,
+                raw: true,
+                websocket: None,
+                exec: Some(exec),
+            });
+        }
+
+        let has_scheme = uri.split('?').next().unwrap_or_default().contains("://");
+        let url = if !has_scheme {
             Url::parse(format!("telnet://{}", uri).as_str())
         } else {
             Url::parse(uri)
@@ -31,9 +63,16 @@ impl Uri {
                     return Err(io::ErrorKind::AddrNotAvailable.into());
                 };
 
-                let tls = match url.scheme() {
-                    "telnet" => false,
-                    "ssl" | "tls" => true,
+                let mut raw = false;
+                let (tls, mut websocket) = match url.scheme() {
+                    "telnet" => (false, None),
+                    "raw" => {
+                        raw = true;
+                        (false, None)
+                    }
+                    "ssl" | "tls" => (true, None),
+                    "ws" => (false, Some(WebSocketOptions::default())),
+                    "wss" => (true, Some(WebSocketOptions::default())),
                     _ => {
                         return Err(io::Error::new(
                             io::ErrorKind::AddrNotAvailable,
@@ -42,7 +81,56 @@ impl Uri {
                     }
                 };
 
-                Ok(Self { host, port, tls })
+                let mut tls_options = TlsOptions::default();
+                let mut proxy = None;
+                let mut passthrough = form_urlencoded::Serializer::new(String::new());
+                let mut has_passthrough = false;
+                for (key, value) in url.query_pairs() {
+                    if key == "proxy" {
+                        proxy = Some(Proxy::from_string(&value)?);
+                    } else if key == "raw" && websocket.is_none() {
+                        raw = !matches!(value.as_ref(), "" | "0" | "false");
+                    } else if tls_options.set_param(&key, &value) {
+                        // Handled
+                    } else if let Some(websocket) = websocket.as_mut() {
+                        if !websocket.set_param(&key, &value) {
+                            // Params we don't recognize are probably meant for the server
+                            passthrough.append_pair(&key, &value);
+                            has_passthrough = true;
+                        }
+                    } else {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("Unexpected parameter: {key}"),
+                        ));
+                    }
+                }
+
+                if !tls && tls_options != TlsOptions::default() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "TLS parameters require an ssl://, tls://, or wss:// uri",
+                    ));
+                }
+
+                if let Some(websocket) = websocket.as_mut() {
+                    websocket.resource = url.path().to_string();
+                    if has_passthrough {
+                        websocket.resource.push('?');
+                        websocket.resource.push_str(&passthrough.finish());
+                    }
+                }
+
+                Ok(Self {
+                    host,
+                    port,
+                    tls,
+                    tls_options,
+                    proxy,
+                    raw,
+                    websocket,
+                    exec: None,
+                })
             }
 
             Err(e) => Err(io::Error::new(io::ErrorKind::AddrNotAvailable, e)),
@@ -73,4 +161,74 @@ mod tests {
         assert_eq!(uri.port, 12358);
         assert_eq!(uri.tls, true);
     }
+
+    #[test]
+    fn tls_params_test() {
+        let uri =
+            parse_ok("ssl://thegoodplace.com:12358?ca=/tmp/ca.pem&insecure=1&fingerprint=AB:CD");
+        assert!(uri.tls);
+        assert_eq!(uri.tls_options.ca, Some("/tmp/ca.pem".into()));
+        assert!(uri.tls_options.insecure);
+        assert_eq!(uri.tls_options.fingerprint, Some("AB:CD".to_string()));
+
+        assert!(Uri::from_string("thegoodplace.com:12358?insecure=1").is_err());
+        assert!(Uri::from_string("ssl://thegoodplace.com:12358?jeremy=bearimy").is_err());
+    }
+
+    #[test]
+    fn proxy_param_test() {
+        let uri = parse_ok("thegoodplace.com:12358?proxy=socks5://127.0.0.1:9050");
+        let proxy = uri.proxy.expect("Expected a proxy");
+        assert_eq!(proxy.host, "127.0.0.1");
+        assert_eq!(proxy.port, 9050);
+    }
+
+    #[test]
+    fn raw_test() {
+        let uri = parse_ok("raw://thegoodplace.com:12358");
+        assert_eq!(uri.host, "thegoodplace.com");
+        assert!(uri.raw);
+        assert!(!uri.tls);
+
+        let uri = parse_ok("ssl://thegoodplace.com:12358?raw=1");
+        assert!(uri.raw);
+        assert!(uri.tls);
+
+        assert!(!parse_ok("thegoodplace.com:12358").raw);
+    }
+
+    #[test]
+    fn exec_test() {
+        let uri = parse_ok("exec:dfrotz -p 'zork 1.z5'");
+        let exec = uri.exec.expect("Expected exec options");
+        assert_eq!(exec.command, "dfrotz -p 'zork 1.z5'");
+        assert!(!exec.pty);
+
+        let exec = parse_ok("exec+pty:./mud").exec.unwrap();
+        assert_eq!(exec.command, "./mud");
+        assert!(exec.pty);
+    }
+
+    #[test]
+    fn websocket_test() {
+        let uri = parse_ok("wss://thegoodplace.com/play?telnet=1&insecure=1&room=42");
+        assert_eq!(uri.host, "thegoodplace.com");
+        assert_eq!(uri.port, 443);
+        assert!(uri.tls);
+        assert!(uri.tls_options.insecure);
+
+        let websocket = uri.websocket.expect("Expected websocket options");
+        assert!(websocket.telnet);
+        assert_eq!(websocket.resource, "/play?room=42");
+        assert_eq!(
+            websocket.url(&uri.host, uri.port, uri.tls),
+            "wss://thegoodplace.com:443/play?room=42"
+        );
+
+        let uri = parse_ok("ws://thegoodplace.com:4000");
+        assert!(!uri.tls);
+        assert_eq!(uri.port, 4000);
+        assert!(!uri.websocket.as_ref().unwrap().telnet);
+        assert_eq!(uri.websocket.unwrap().resource, "/");
+    }
 }
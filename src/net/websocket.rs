@@ -0,0 +1,30 @@
+/// Options for WebSocket connections (IE: `ws://host:port/path?telnet=1`)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WebSocketOptions {
+    /// The path (and any query params not consumed by us) to request
+    pub resource: String,
+
+    /// If set, frames carry telnet bytes and will be processed as such (so
+    /// GMCP, MSDP, etc. work as usual); otherwise they are treated as plain text
+    pub telnet: bool,
+
+    /// A subprotocol to request via `Sec-WebSocket-Protocol`
+    pub protocol: Option<String>,
+}
+
+impl WebSocketOptions {
+    pub fn set_param(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "telnet" => self.telnet = !matches!(value, "" | "0" | "false"),
+            "protocol" => self.protocol = Some(value.to_string()),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Build the URL to request from the server
+    pub fn url(&self, host: &str, port: u16, tls: bool) -> String {
+        let scheme = if tls { "wss" } else { "ws" };
+        format!("{scheme}://{host}:{port}{}", self.resource)
+    }
+}
@@ -40,7 +40,7 @@ impl<R: ProcessorOutputReceiver> TestBed<R> {
             .connections
             .get_processor(self.id)
             .unwrap();
-        handle_sent_text(&mut self.ui, processor, to_send.to_string())
+        handle_sent_text(&mut self.ui, processor, to_send.to_string(), true)
     }
 
     pub fn register_prompt(&mut self, group_id: Id, prompt_index: usize, prompt: &str) {
@@ -56,6 +56,7 @@ impl<R: ProcessorOutputReceiver> TestBed<R> {
             matcher,
             group_id,
             prompt_index,
+            Default::default(),
         );
     }
 }
@@ -0,0 +1,328 @@
+use std::{error::Error, fmt::Display, io, net::SocketAddr, time::Duration};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+
+use crate::net::{
+    proxy::Proxy,
+    tls::{fingerprint, TlsOptions},
+};
+use tokio::{
+    net::{lookup_host, TcpStream},
+    time,
+};
+use tokio_native_tls::{TlsConnector, TlsStream};
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for an attempt to connect to one address before also trying
+/// the next one, per RFC 8305 ("Happy Eyeballs")
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Failures establishing a connection that are worth distinguishing from general
+/// I/O errors. These are wrapped in an [io::Error]; use [ConnectError::find] to
+/// get them back out.
+#[derive(Debug)]
+pub enum ConnectError {
+    /// The host could not be resolved
+    Dns(io::Error),
+
+    /// The TLS handshake failed
+    Tls(native_tls::Error),
+
+    /// The server's certificate did not have the fingerprint we expected
+    FingerprintMismatch { expected: String, actual: String },
+}
+
+impl ConnectError {
+    pub fn find(error: &io::Error) -> Option<&ConnectError> {
+        error.get_ref()?.downcast_ref()
+    }
+}
+
+impl Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectError::Dns(err) => write!(f, "Failed to resolve host: {err}"),
+            ConnectError::Tls(err) => write!(f, "TLS handshake failed: {err}"),
+            ConnectError::FingerprintMismatch { expected, actual } => write!(
+                f,
+                "Certificate fingerprint {actual} does not match expected {expected}"
+            ),
+        }
+    }
+}
+
+impl Error for ConnectError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConnectError::Dns(err) => Some(err),
+            ConnectError::Tls(err) => Some(err),
+            ConnectError::FingerprintMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<ConnectError> for io::Error {
+    fn from(value: ConnectError) -> Self {
+        io::Error::other(value)
+    }
+}
+
+/// Progress updates while establishing a connection
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectStatus {
+    Resolving {
+        host: String,
+    },
+    Trying {
+        address: SocketAddr,
+    },
+    Tunneling {
+        proxy: String,
+    },
+    TimedOut,
+
+    /// The TLS handshake completed; `verified` is false if certificate verification
+    /// was disabled (IE: the certificate may be self-signed)
+    Certificate {
+        fingerprint: String,
+        verified: bool,
+    },
+}
+
+impl Display for ConnectStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectStatus::Resolving { host } => write!(f, "Resolving {host}..."),
+            ConnectStatus::Trying { address } => write!(f, "Trying {address}..."),
+            ConnectStatus::Tunneling { proxy } => write!(f, "Tunneling through {proxy}..."),
+            ConnectStatus::TimedOut => write!(f, "Timed out."),
+            ConnectStatus::Certificate { fingerprint, .. } => {
+                write!(f, "Server certificate: {fingerprint}")
+            }
+        }
+    }
+}
+
+pub struct ConnectOptions<'a> {
+    /// How long to wait for the connection to be established, including DNS
+    /// resolution and (if applicable) the TLS handshake
+    pub timeout: Duration,
+
+    /// If provided, connections will be tunneled through this proxy
+    pub proxy: Option<Proxy>,
+
+    pub on_status: Box<dyn FnMut(ConnectStatus) + Send + 'a>,
+}
+
+impl Default for ConnectOptions<'_> {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_CONNECT_TIMEOUT,
+            proxy: None,
+            on_status: Box::new(|_| {}),
+        }
+    }
+}
+
+impl ConnectOptions<'_> {
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
+
+    pub(crate) fn report(&mut self, status: ConnectStatus) {
+        (self.on_status)(status)
+    }
+
+    /// Report that we timed out, returning an appropriate error
+    pub(crate) fn timed_out(&mut self) -> io::Error {
+        self.report(ConnectStatus::TimedOut);
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("Timed out after {}s", self.timeout.as_secs_f32()),
+        )
+    }
+}
+
+/// Alternate between address families (starting with whichever the resolver
+/// preferred) so a broken IPv6 (or IPv4) route doesn't have to time out for
+/// every address before we try the other family
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+    let prefer_v6 = first.is_ipv6();
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == prefer_v6);
+
+    let mut interleaved = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => break,
+            (a, b) => interleaved.extend(a.into_iter().chain(b)),
+        }
+    }
+    interleaved
+}
+
+async fn connect_any(
+    addrs: Vec<SocketAddr>,
+    options: &mut ConnectOptions<'_>,
+) -> io::Result<TcpStream> {
+    let mut remaining = addrs.into_iter().peekable();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+
+    loop {
+        if let Some(address) = remaining.next() {
+            options.report(ConnectStatus::Trying { address });
+            attempts.push(TcpStream::connect(address));
+        }
+
+        if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::AddrNotAvailable,
+                    "No addresses to connect to",
+                )
+            }));
+        }
+
+        tokio::select! {
+            Some(result) = attempts.next() => match result {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_error = Some(err),
+            },
+
+            _ = time::sleep(CONNECTION_ATTEMPT_DELAY), if remaining.peek().is_some() => {},
+        }
+    }
+}
+
+/// Connect to any of the addresses `host` resolves to
+async fn connect_direct(
+    host: &str,
+    port: u16,
+    options: &mut ConnectOptions<'_>,
+) -> io::Result<TcpStream> {
+    options.report(ConnectStatus::Resolving {
+        host: host.to_string(),
+    });
+    let addrs: Vec<SocketAddr> = lookup_host((host, port))
+        .await
+        .map_err(ConnectError::Dns)?
+        .collect();
+
+    connect_any(interleave_families(addrs), options).await
+}
+
+/// Connect to `host`, either directly or through the proxy in `options`
+pub(crate) async fn connect_tcp(
+    host: &str,
+    port: u16,
+    options: &mut ConnectOptions<'_>,
+) -> io::Result<TcpStream> {
+    let Some(proxy) = options.proxy.clone() else {
+        return connect_direct(host, port, options).await;
+    };
+
+    let mut stream = connect_direct(&proxy.host, proxy.port, options).await?;
+    options.report(ConnectStatus::Tunneling {
+        proxy: proxy.to_string(),
+    });
+    proxy.tunnel(&mut stream, host, port).await?;
+    Ok(stream)
+}
+
+/// Connect to `host` with [connect_tcp] and perform a TLS handshake, verifying
+/// the server's certificate fingerprint if `tls` requires one
+pub(crate) async fn connect_tls(
+    host: &str,
+    port: u16,
+    tls: &TlsOptions,
+    options: &mut ConnectOptions<'_>,
+) -> io::Result<TlsStream<TcpStream>> {
+    let cx = TlsConnector::from(tls.connector().await?);
+    let tcp = connect_tcp(host, port, options).await?;
+    let stream = cx.connect(host, tcp).await.map_err(ConnectError::Tls)?;
+
+    let certificate = stream
+        .get_ref()
+        .peer_certificate()
+        .and_then(|cert| cert.map(|cert| cert.to_der()).transpose())
+        .map_err(ConnectError::Tls)?;
+    if let Some(der) = certificate {
+        let fingerprint = fingerprint(&der);
+        if !tls.accepts_fingerprint(&fingerprint) {
+            return Err(ConnectError::FingerprintMismatch {
+                expected: tls.fingerprint.clone().unwrap_or_default(),
+                actual: fingerprint,
+            }
+            .into());
+        }
+
+        options.report(ConnectStatus::Certificate {
+            fingerprint,
+            verified: !tls.insecure,
+        });
+    }
+
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn interleave_families_test() {
+        let addrs: Vec<SocketAddr> = ["[::1]:23", "[::2]:23", "[::3]:23", "1.2.3.4:23"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect();
+        let interleaved: Vec<String> = interleave_families(addrs)
+            .iter()
+            .map(|addr| addr.to_string())
+            .collect();
+        assert_eq!(
+            interleaved,
+            vec!["[::1]:23", "1.2.3.4:23", "[::2]:23", "[::3]:23"]
+        );
+    }
+
+    #[tokio::test]
+    async fn fall_back_to_next_address_test() -> io::Result<()> {
+        let closed = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let open = listener.local_addr()?;
+
+        let statuses = Mutex::new(Vec::new());
+        let mut options = ConnectOptions {
+            on_status: Box::new(|status| statuses.lock().unwrap().push(status)),
+            ..Default::default()
+        };
+
+        let stream = connect_any(vec![closed, open], &mut options).await?;
+        assert_eq!(stream.peer_addr()?, open);
+        drop(options);
+
+        assert_eq!(
+            *statuses.lock().unwrap(),
+            vec![
+                ConnectStatus::Trying { address: closed },
+                ConnectStatus::Trying { address: open },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -2,40 +2,119 @@ use std::{collections::HashMap, io};
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use serde::Serialize;
-
-use crate::{daemon::protocol::replay::ReplayConfig, net::Uri, transport::replay::ReplayTransport};
-
-use self::telnet::TelnetTransport;
-
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    daemon::protocol::replay::ReplayConfig,
+    net::{encoding::TextEncoding, proxy::Proxy, Uri},
+    transport::replay::{RecordingReplayTransport, ReplayTransport},
+};
+
+use self::{
+    process::ProcessTransport, raw::RawTransport, telnet::TelnetTransport, websocket::WebSocketIo,
+};
+
+mod connect;
+mod process;
+mod raw;
+pub mod recording;
 mod replay;
 pub mod telnet;
+mod websocket;
 
-#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub use connect::{ConnectError, ConnectOptions, ConnectStatus};
+pub use process::ProcessExited;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum TransportEventValue {
     String(String),
     Vec(Vec<TransportEventValue>),
     Map(HashMap<String, TransportEventValue>),
+    Bool(bool),
+    Number(serde_json::Number),
+    Null,
+}
+
+impl From<serde_json::Value> for TransportEventValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => TransportEventValue::Null,
+            serde_json::Value::Bool(b) => TransportEventValue::Bool(b),
+            serde_json::Value::Number(n) => TransportEventValue::Number(n),
+            serde_json::Value::String(s) => TransportEventValue::String(s),
+            serde_json::Value::Array(items) => {
+                TransportEventValue::Vec(items.into_iter().map(|item| item.into()).collect())
+            }
+            serde_json::Value::Object(map) => TransportEventValue::Map(
+                map.into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+        }
+    }
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct EventData {
     ns: String,
     name: String,
     payload: Option<TransportEventValue>,
 }
 
+impl EventData {
+    pub fn new(ns: String, name: String, payload: Option<TransportEventValue>) -> Self {
+        Self { ns, name, payload }
+    }
+
+    pub fn ns(&self) -> &str {
+        &self.ns
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn into_payload(self) -> Option<TransportEventValue> {
+        self.payload
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum TransportEvent {
     Data(Bytes),
     Event(EventData),
     EndOfPrompt,
+
+    /// The server has agreed to (or stopped) echoing our input; it usually does this
+    /// when prompting for a password, so we should not echo it locally
+    ServerEcho {
+        enabled: bool,
+    },
+
+    /// The server sent Go Ahead without having negotiated SuppressGoAhead. Many older
+    /// servers that don't support EOR use this to mark the end of a prompt.
+    GoAhead,
+
+    /// The server has agreed (via CHARSET negotiation) to send text in this encoding
+    EncodingChanged(TextEncoding),
+
+    /// The server has enabled (or disabled) MXP
+    Mxp {
+        enabled: bool,
+    },
     Nop,
 }
 
 pub enum TransportNotification {
     WindowSizeUnavailable,
-    WindowSize { width: u16, height: u16 },
+    WindowSize {
+        width: u16,
+        height: u16,
+    },
+
+    /// Client-provided environment variables (IE: for MNES) to add to or override defaults
+    Environment(HashMap<String, String>),
 }
 
 #[async_trait]
@@ -43,6 +122,16 @@ pub trait Transport {
     async fn read(&mut self) -> io::Result<TransportEvent>;
     async fn write(&mut self, data: &[u8]) -> io::Result<usize>;
     async fn notify(&mut self, notification: TransportNotification) -> io::Result<()>;
+
+    /// Send an out-of-band event (IE: GMCP or MSDP) to the server
+    async fn send_event(&mut self, event: EventData) -> io::Result<()>;
+
+    /// Send something that keeps the connection alive without being interpreted
+    /// as a command (IE: a telnet NOP), if the protocol supports it
+    async fn send_keepalive(&mut self) -> io::Result<()>;
+
+    /// Cleanly close the underlying connection
+    async fn close(&mut self) -> io::Result<()>;
 }
 
 pub struct BoxedTransport(Box<dyn Transport + Send>);
@@ -53,19 +142,77 @@ impl BoxedTransport {
     }
 
     pub async fn replay(config: ReplayConfig, buffer_size: usize) -> io::Result<BoxedTransport> {
+        if RecordingReplayTransport::is_recording(&config.path).await? {
+            return Ok(BoxedTransport::from(
+                RecordingReplayTransport::for_replay(config).await?,
+            ));
+        }
+
+        // Legacy dumps of raw bytes:
         let stream = ReplayTransport::for_replay(config).await?;
         Ok(BoxedTransport::from(
             TelnetTransport::connect_with_stream(stream, buffer_size).await?,
         ))
     }
 
-    pub async fn connect_uri(uri: Uri, buffer_size: usize) -> io::Result<BoxedTransport> {
-        Ok(if uri.tls {
+    pub async fn connect_uri(
+        uri: Uri,
+        buffer_size: usize,
+        mut options: ConnectOptions<'_>,
+    ) -> io::Result<BoxedTransport> {
+        if let Some(exec) = &uri.exec {
+            return Ok(BoxedTransport::from(ProcessTransport::spawn(
+                exec,
+                buffer_size,
+            )?));
+        }
+
+        if options.proxy.is_none() {
+            options.proxy = match uri.proxy {
+                Some(ref proxy) => Some(proxy.clone()),
+                None => Proxy::from_env()?,
+            };
+        }
+
+        Ok(if let Some(websocket) = &uri.websocket {
+            let stream = WebSocketIo::connect(&uri, websocket, &mut options).await?;
+            if websocket.telnet {
+                BoxedTransport::from(
+                    TelnetTransport::connect_with_stream(stream, buffer_size).await?,
+                )
+            } else {
+                BoxedTransport::from(RawTransport::new(stream, buffer_size))
+            }
+        } else if uri.raw && uri.tls {
+            BoxedTransport::from(
+                RawTransport::connect_tls(
+                    &uri.host,
+                    uri.port,
+                    &uri.tls_options,
+                    buffer_size,
+                    &mut options,
+                )
+                .await?,
+            )
+        } else if uri.raw {
+            BoxedTransport::from(
+                RawTransport::connect(&uri.host, uri.port, buffer_size, &mut options).await?,
+            )
+        } else if uri.tls {
             BoxedTransport::from(
-                TelnetTransport::connect_tls(&uri.host, uri.port, buffer_size).await?,
+                TelnetTransport::connect_tls(
+                    &uri.host,
+                    uri.port,
+                    &uri.tls_options,
+                    buffer_size,
+                    &mut options,
+                )
+                .await?,
             )
         } else {
-            BoxedTransport::from(TelnetTransport::connect(&uri.host, uri.port, buffer_size).await?)
+            BoxedTransport::from(
+                TelnetTransport::connect(&uri.host, uri.port, buffer_size, &mut options).await?,
+            )
         })
     }
 }
@@ -83,4 +230,16 @@ impl Transport for BoxedTransport {
     async fn notify(&mut self, notification: TransportNotification) -> io::Result<()> {
         (*self.0).notify(notification).await
     }
+
+    async fn send_event(&mut self, event: EventData) -> io::Result<()> {
+        (*self.0).send_event(event).await
+    }
+
+    async fn send_keepalive(&mut self) -> io::Result<()> {
+        (*self.0).send_keepalive().await
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        (*self.0).close().await
+    }
 }
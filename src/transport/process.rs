@@ -0,0 +1,398 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io,
+    pin::Pin,
+    process::{ExitStatus, Stdio},
+    task::{Context, Poll},
+};
+
+use async_trait::async_trait;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    process::{Child, Command},
+};
+
+use crate::net::exec::ExecOptions;
+
+use super::{raw::RawTransport, EventData, Transport, TransportEvent, TransportNotification};
+
+/// The local process we were running in place of a connection has exited. This
+/// is wrapped in an [io::Error]; use [ProcessExited::find] to get it back out.
+#[derive(Debug)]
+pub struct ProcessExited(pub ExitStatus);
+
+impl ProcessExited {
+    pub fn find(error: &io::Error) -> Option<&ProcessExited> {
+        error.get_ref()?.downcast_ref()
+    }
+}
+
+impl Display for ProcessExited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.code() {
+            Some(code) => write!(f, "Process exited with code {code}"),
+            None => write!(f, "Process exited ({})", self.0),
+        }
+    }
+}
+
+impl Error for ProcessExited {}
+
+impl From<ProcessExited> for io::Error {
+    fn from(value: ProcessExited) -> Self {
+        io::Error::other(value)
+    }
+}
+
+/// Joins the output(s) and input of a process into a single stream. Reads
+/// from whichever output has data, and only reaches EOF once all have.
+struct ProcessIo {
+    outputs: Vec<Pin<Box<dyn AsyncRead + Send>>>,
+    input: Pin<Box<dyn AsyncWrite + Send>>,
+}
+
+impl AsyncRead for ProcessIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut index = 0;
+        while index < self.outputs.len() {
+            let original_len = buf.filled().len();
+            match self.outputs[index].as_mut().poll_read(cx, buf) {
+                Poll::Ready(Ok(())) if buf.filled().len() > original_len => {
+                    return Poll::Ready(Ok(()))
+                }
+
+                // NOTE: When the process exits, reading from a pty fails with EIO
+                // instead of reaching EOF
+                #[cfg(unix)]
+                Poll::Ready(Err(err)) if err.raw_os_error() == Some(libc::EIO) => {
+                    self.outputs.remove(index);
+                }
+
+                Poll::Ready(Ok(())) => {
+                    self.outputs.remove(index);
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => index += 1,
+            }
+        }
+
+        if self.outputs.is_empty() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl AsyncWrite for ProcessIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.input.as_mut().poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.input.as_mut().poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.input.as_mut().poll_shutdown(cx)
+    }
+}
+
+/// A [Transport] that runs a local command, treating its stdin and stdout (and
+/// stderr) as the connection
+pub struct ProcessTransport {
+    child: Child,
+    io: RawTransport<ProcessIo>,
+
+    #[cfg(unix)]
+    pty: Option<std::fs::File>,
+}
+
+impl ProcessTransport {
+    pub fn spawn(exec: &ExecOptions, buffer_size: usize) -> io::Result<Self> {
+        let mut command = shell_command(&exec.command);
+
+        // Put the shell in its own process group so we can clean up anything it
+        // starts; see [kill_process_group]. (Under a pty it gets its own session)
+        #[cfg(unix)]
+        if !exec.pty {
+            std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        }
+
+        let mut command = Command::from(command);
+        command.kill_on_drop(true);
+
+        if exec.pty {
+            return Self::spawn_pty(command, buffer_size);
+        }
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let (Some(stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            return Err(io::Error::other("Failed to open process stdio"));
+        };
+
+        let io = ProcessIo {
+            outputs: vec![Box::pin(stdout), Box::pin(stderr)],
+            input: Box::pin(stdin),
+        };
+        Ok(Self {
+            child,
+            io: RawTransport::new(io, buffer_size),
+            #[cfg(unix)]
+            pty: None,
+        })
+    }
+
+    #[cfg(unix)]
+    fn spawn_pty(mut command: Command, buffer_size: usize) -> io::Result<Self> {
+        let (master, slave) = pty::open()?;
+        command
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+        pty::set_controlling_terminal(&mut command);
+
+        let child = command.spawn()?;
+
+        // Drop the command so its copies of the slave fd get closed; otherwise we
+        // would never see the process's end of the pty close
+        drop(command);
+
+        let master = std::fs::File::from(master);
+        let io = ProcessIo {
+            outputs: vec![Box::pin(tokio::fs::File::from_std(master.try_clone()?))],
+            input: Box::pin(tokio::fs::File::from_std(master.try_clone()?)),
+        };
+        Ok(Self {
+            child,
+            io: RawTransport::new(io, buffer_size),
+            pty: Some(master),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn spawn_pty(_command: Command, _buffer_size: usize) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Running commands under a pty is not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> std::process::Command {
+    let mut shell = std::process::Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> std::process::Command {
+    let mut shell = std::process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+/// Killing the shell alone would leave the command it ran (and anything else
+/// it started) orphaned, so kill its whole process group
+#[cfg(unix)]
+fn kill_process_group(child: &Child) {
+    if let Some(pid) = child.id() {
+        // SAFETY: kill has no memory safety requirements
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+}
+
+impl Drop for ProcessTransport {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        kill_process_group(&self.child);
+    }
+}
+
+#[async_trait]
+impl Transport for ProcessTransport {
+    async fn read(&mut self) -> io::Result<TransportEvent> {
+        match self.io.read().await {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                let status = self.child.wait().await?;
+                Err(ProcessExited(status).into())
+            }
+            result => result,
+        }
+    }
+
+    async fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        // Local processes expect local line endings, not the network's \r\n
+        let local: Vec<u8> = data
+            .iter()
+            .enumerate()
+            .filter(|(i, b)| !(**b == b'\r' && data.get(i + 1) == Some(&b'\n')))
+            .map(|(_, b)| *b)
+            .collect();
+        self.io.write(&local).await?;
+        Ok(data.len())
+    }
+
+    async fn notify(&mut self, notification: TransportNotification) -> io::Result<()> {
+        #[cfg(unix)]
+        if let (Some(pty), TransportNotification::WindowSize { width, height }) =
+            (&self.pty, &notification)
+        {
+            pty::set_window_size(pty, *width, *height)?;
+        }
+
+        self.io.notify(notification).await
+    }
+
+    async fn send_event(&mut self, event: EventData) -> io::Result<()> {
+        self.io.send_event(event).await
+    }
+
+    async fn send_keepalive(&mut self) -> io::Result<()> {
+        self.io.send_keepalive().await
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        // Closing stdin may be enough for some processes to exit, but we can't
+        // count on that
+        self.io.close().await.ok();
+
+        #[cfg(unix)]
+        kill_process_group(&self.child);
+
+        self.child.kill().await
+    }
+}
+
+#[cfg(unix)]
+mod pty {
+    use std::{
+        fs::File,
+        io,
+        os::fd::{AsRawFd, FromRawFd, OwnedFd},
+        ptr,
+    };
+
+    use tokio::process::Command;
+
+    /// Open a pty, returning the (master, slave) pair. Local echo is disabled,
+    /// since we echo sent text ourselves.
+    pub fn open() -> io::Result<(OwnedFd, OwnedFd)> {
+        let mut master = -1;
+        let mut slave = -1;
+
+        // SAFETY: openpty only writes the new fds to the provided pointers, and the
+        // name/termios/winsize args are allowed to be null
+        let result = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        if result == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // SAFETY: openpty succeeded, so these are valid fds that nobody else owns
+        let (master, slave) =
+            unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+        // SAFETY: These only read/write the termios struct we provide and the fd
+        // flags of fds we own
+        unsafe {
+            if libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(slave.as_raw_fd(), &mut termios) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            termios.c_lflag &= !libc::ECHO;
+            if libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok((master, slave))
+    }
+
+    /// Make the pty the process's stdio is attached to its controlling terminal,
+    /// so it receives job control signals, window size changes, etc.
+    pub fn set_controlling_terminal(command: &mut Command) {
+        // SAFETY: setsid and ioctl are async-signal-safe, and we don't allocate
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    pub fn set_window_size(pty: &File, width: u16, height: u16) -> io::Result<()> {
+        let size = libc::winsize {
+            ws_row: height,
+            ws_col: width,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+
+        // SAFETY: TIOCSWINSZ only reads the winsize struct we provide
+        if unsafe { libc::ioctl(pty.as_raw_fd(), libc::TIOCSWINSZ, &size) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn pipes_test() -> io::Result<()> {
+        let exec = ExecOptions {
+            command: "read line; echo \"you said $line\"; exit 3".to_string(),
+            pty: false,
+        };
+        let mut transport = ProcessTransport::spawn(&exec, 4096)?;
+        transport.write(b"hello\r\n").await?;
+
+        assert_matches!(transport.read().await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"you said hello\n");
+        });
+
+        let err = transport.read().await.unwrap_err();
+        let exited = ProcessExited::find(&err).expect("Expected ProcessExited");
+        assert_eq!(exited.0.code(), Some(3));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::io;
+
+use async_trait::async_trait;
+use bytes::BytesMut;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+    time,
+};
+use tokio_native_tls::TlsStream;
+
+use crate::net::tls::TlsOptions;
+
+use super::{
+    connect::{connect_tcp, connect_tls, ConnectOptions},
+    EventData, Transport, TransportEvent, TransportNotification,
+};
+
+/// A [Transport] that passes bytes through as-is, with no telnet processing or
+/// option negotiation
+pub struct RawTransport<S: AsyncRead + AsyncWrite> {
+    stream: S,
+    buffer: BytesMut,
+    buffer_size: usize,
+}
+
+impl RawTransport<TcpStream> {
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        buffer_size: usize,
+        options: &mut ConnectOptions<'_>,
+    ) -> io::Result<Self> {
+        let timeout = options.timeout;
+        let stream = time::timeout(timeout, connect_tcp(host, port, options))
+            .await
+            .map_err(|_| options.timed_out())??;
+        Ok(Self::new(stream, buffer_size))
+    }
+}
+
+impl RawTransport<TlsStream<TcpStream>> {
+    pub async fn connect_tls(
+        host: &str,
+        port: u16,
+        tls: &TlsOptions,
+        buffer_size: usize,
+        options: &mut ConnectOptions<'_>,
+    ) -> io::Result<Self> {
+        let timeout = options.timeout;
+        let stream = time::timeout(timeout, connect_tls(host, port, tls, options))
+            .await
+            .map_err(|_| options.timed_out())??;
+        Ok(Self::new(stream, buffer_size))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> RawTransport<S> {
+    pub fn new(stream: S, buffer_size: usize) -> Self {
+        Self {
+            stream,
+            buffer: BytesMut::with_capacity(buffer_size),
+            buffer_size,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Transport for RawTransport<S> {
+    async fn read(&mut self) -> io::Result<TransportEvent> {
+        self.buffer.reserve(self.buffer_size);
+        if self.stream.read_buf(&mut self.buffer).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(TransportEvent::Data(self.buffer.split().freeze()))
+    }
+
+    async fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.stream.write_all(data).await?;
+        self.stream.flush().await?;
+        Ok(data.len())
+    }
+
+    async fn notify(&mut self, _notification: TransportNotification) -> io::Result<()> {
+        // There's nobody to tell
+        Ok(())
+    }
+
+    async fn send_event(&mut self, _event: EventData) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Events are not supported without telnet",
+        ))
+    }
+
+    async fn send_keepalive(&mut self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Keepalives are not supported without telnet",
+        ))
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        self.stream.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn passthrough_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut transport = RawTransport::new(local, 4096);
+
+        remote.write_all(b"Hi \xFF\xF9there").await?;
+        assert_matches!(transport.read().await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"Hi \xFF\xF9there");
+        });
+
+        transport.write(b"\xFF").await?;
+        let mut received = [0u8; 1];
+        remote.read_exact(&mut received).await?;
+        assert_eq!(received, [0xFF]);
+
+        drop(remote);
+        assert_eq!(
+            transport.read().await.unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        Ok(())
+    }
+}
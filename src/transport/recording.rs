@@ -0,0 +1,290 @@
+//! Session recordings, as written to the file named by `KODACHI_DUMP`.
+//!
+//! A recording is a sequence of sessions (one per connection attempt), each of
+//! which starts with [MAGIC] followed by any number of records:
+//!
+//! ```text
+//! tag: u8 | elapsed_ms: u64 (LE) | len: u32 (LE) | payload: [u8; len]
+//! ```
+//!
+//! Where `elapsed_ms` is relative to the start of the session, and `tag` is one of:
+//!
+//! - `<`: Text received from the server (after telnet processing)
+//! - `>`: Text sent to the server
+//! - `{`: A [RecordedEvent] received from the server, as JSON
+//! - `}`: An [EventData] sent to the server, as JSON
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::net::encoding::TextEncoding;
+
+use super::{EventData, TransportEvent};
+
+pub const MAGIC: &[u8; 8] = b"KDCHREC1";
+
+const TAG_INBOUND_DATA: u8 = b'<';
+const TAG_OUTBOUND_DATA: u8 = b'>';
+const TAG_INBOUND_EVENT: u8 = b'{';
+const TAG_OUTBOUND_EVENT: u8 = b'}';
+
+/// The non-Data [TransportEvent]s worth recording
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum RecordedEvent {
+    Event { event: EventData },
+    EndOfPrompt,
+    GoAhead,
+    ServerEcho { enabled: bool },
+    EncodingChanged { encoding: String },
+    Mxp { enabled: bool },
+}
+
+impl RecordedEvent {
+    fn from_transport(event: &TransportEvent) -> Option<Self> {
+        Some(match event {
+            TransportEvent::Event(event) => RecordedEvent::Event {
+                event: event.clone(),
+            },
+            TransportEvent::EndOfPrompt => RecordedEvent::EndOfPrompt,
+            TransportEvent::GoAhead => RecordedEvent::GoAhead,
+            TransportEvent::ServerEcho { enabled } => {
+                RecordedEvent::ServerEcho { enabled: *enabled }
+            }
+            TransportEvent::EncodingChanged(encoding) => RecordedEvent::EncodingChanged {
+                encoding: encoding.name().to_string(),
+            },
+            TransportEvent::Mxp { enabled } => RecordedEvent::Mxp { enabled: *enabled },
+            TransportEvent::Data(_) | TransportEvent::Nop => return None,
+        })
+    }
+
+    pub fn into_transport(self) -> TransportEvent {
+        match self {
+            RecordedEvent::Event { event } => TransportEvent::Event(event),
+            RecordedEvent::EndOfPrompt => TransportEvent::EndOfPrompt,
+            RecordedEvent::GoAhead => TransportEvent::GoAhead,
+            RecordedEvent::ServerEcho { enabled } => TransportEvent::ServerEcho { enabled },
+            RecordedEvent::EncodingChanged { encoding } => TextEncoding::for_label(&encoding)
+                .map(TransportEvent::EncodingChanged)
+                .unwrap_or(TransportEvent::Nop),
+            RecordedEvent::Mxp { enabled } => TransportEvent::Mxp { enabled },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Record {
+    InboundData(Bytes),
+    OutboundData(Bytes),
+    InboundEvent(RecordedEvent),
+    OutboundEvent(EventData),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    /// When this record happened, relative to the start of the recording
+    pub elapsed: Duration,
+    pub record: Record,
+}
+
+pub struct Recorder<W: Write = File> {
+    output: W,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Start a new session, appending to any existing recording at `path`
+    pub fn append_to(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(File::options().append(true).create(true).open(path)?)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut output: W) -> io::Result<Self> {
+        output.write_all(MAGIC)?;
+        Ok(Self {
+            output,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn inbound(&mut self, event: &TransportEvent) -> io::Result<()> {
+        if let TransportEvent::Data(data) = event {
+            self.write(TAG_INBOUND_DATA, data)
+        } else if let Some(event) = RecordedEvent::from_transport(event) {
+            self.write(TAG_INBOUND_EVENT, &serde_json::to_vec(&event)?)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn outbound_data(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write(TAG_OUTBOUND_DATA, data)
+    }
+
+    pub fn outbound_event(&mut self, event: &EventData) -> io::Result<()> {
+        self.write(TAG_OUTBOUND_EVENT, &serde_json::to_vec(event)?)
+    }
+
+    fn write(&mut self, tag: u8, payload: &[u8]) -> io::Result<()> {
+        let elapsed = self.started.elapsed().as_millis() as u64;
+        let mut record = Vec::with_capacity(13 + payload.len());
+        record.push(tag);
+        record.extend_from_slice(&elapsed.to_le_bytes());
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(payload);
+        self.output.write_all(&record)
+    }
+}
+
+pub struct RecordingReader<R: AsyncRead + Unpin> {
+    input: R,
+
+    /// The offset applied to timestamps of the current session, so sessions play
+    /// back-to-back
+    session_start: Duration,
+    last_elapsed: Duration,
+}
+
+impl<R: AsyncRead + Unpin> RecordingReader<R> {
+    /// Create a reader for a recording whose [MAGIC] header has already been consumed
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            session_start: Duration::ZERO,
+            last_elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Read the next entry, if any. A truncated final record (IE: if we crashed
+    /// while writing it) is treated as the end of the recording.
+    pub async fn next(&mut self) -> io::Result<Option<Entry>> {
+        match self.read_entry().await {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            result => result.map(Some),
+        }
+    }
+
+    async fn read_entry(&mut self) -> io::Result<Entry> {
+        let mut tag = self.input.read_u8().await?;
+        while tag == MAGIC[0] {
+            let mut rest = [0u8; MAGIC.len() - 1];
+            self.input.read_exact(&mut rest).await?;
+            if rest != MAGIC[1..] {
+                return Err(invalid_data("Corrupt session header"));
+            }
+            self.session_start = self.last_elapsed;
+            tag = self.input.read_u8().await?;
+        }
+
+        let elapsed = self.session_start + Duration::from_millis(self.input.read_u64_le().await?);
+        let len = self.input.read_u32_le().await? as usize;
+        let mut payload = vec![0u8; len];
+        self.input.read_exact(&mut payload).await?;
+        self.last_elapsed = elapsed;
+
+        let record = match tag {
+            TAG_INBOUND_DATA => Record::InboundData(payload.into()),
+            TAG_OUTBOUND_DATA => Record::OutboundData(payload.into()),
+            TAG_INBOUND_EVENT => Record::InboundEvent(serde_json::from_slice(&payload)?),
+            TAG_OUTBOUND_EVENT => Record::OutboundEvent(serde_json::from_slice(&payload)?),
+            _ => return Err(invalid_data(&format!("Unexpected record tag: {tag}"))),
+        };
+
+        Ok(Entry { elapsed, record })
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trip_test() -> io::Result<()> {
+        let mut output = Vec::new();
+        let mut recorder = Recorder::new(&mut output)?;
+        recorder.inbound(&TransportEvent::Data(Bytes::from_static(b"HP: 42> ")))?;
+        recorder.inbound(&TransportEvent::Nop)?;
+        recorder.inbound(&TransportEvent::EndOfPrompt)?;
+        recorder.outbound_data(b"look\r\n")?;
+
+        // A second session:
+        let mut recorder = Recorder::new(&mut output)?;
+        recorder.inbound(&TransportEvent::EncodingChanged(TextEncoding::Cp437))?;
+
+        assert_eq!(&output[..MAGIC.len()], MAGIC);
+        let mut reader = RecordingReader::new(&output[MAGIC.len()..]);
+
+        let records: Vec<Record> = {
+            let mut records = Vec::new();
+            while let Some(entry) = reader.next().await? {
+                records.push(entry.record);
+            }
+            records
+        };
+        assert_eq!(
+            records,
+            vec![
+                Record::InboundData(Bytes::from_static(b"HP: 42> ")),
+                Record::InboundEvent(RecordedEvent::EndOfPrompt),
+                Record::OutboundData(Bytes::from_static(b"look\r\n")),
+                Record::InboundEvent(RecordedEvent::EncodingChanged {
+                    encoding: "IBM437".to_string()
+                }),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sessions_play_back_to_back_test() -> io::Result<()> {
+        let mut input = Vec::new();
+        for (session, elapsed) in [(0, 500u64), (1, 200u64)] {
+            if session > 0 {
+                input.extend_from_slice(MAGIC);
+            }
+            input.push(TAG_INBOUND_DATA);
+            input.extend_from_slice(&elapsed.to_le_bytes());
+            input.extend_from_slice(&1u32.to_le_bytes());
+            input.push(b'!');
+        }
+
+        let mut reader = RecordingReader::new(&input[..]);
+        assert_eq!(
+            reader.next().await?.unwrap().elapsed,
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            reader.next().await?.unwrap().elapsed,
+            Duration::from_millis(700)
+        );
+        assert_eq!(reader.next().await?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn recorded_encoding_test() {
+        let event = RecordedEvent::EncodingChanged {
+            encoding: TextEncoding::Cp437.name().to_string(),
+        };
+        assert!(matches!(
+            event.into_transport(),
+            TransportEvent::EncodingChanged(TextEncoding::Cp437)
+        ));
+    }
+}
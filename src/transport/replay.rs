@@ -1,15 +1,22 @@
-use std::{io, task::Poll};
+use std::{io, path::Path, task::Poll, time::Duration};
 
+use async_trait::async_trait;
 use pin_project::pin_project;
 use tokio::{
     fs,
-    io::{AsyncRead, AsyncWrite},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, BufReader},
     pin,
     sync::oneshot,
+    time::{self, Instant},
 };
 
 use crate::daemon::protocol::replay::ReplayConfig;
 
+use super::{
+    recording::{Record, RecordingReader, MAGIC},
+    EventData, Transport, TransportEvent, TransportNotification,
+};
+
 #[pin_project]
 pub struct ReplayTransport {
     #[pin]
@@ -74,3 +81,257 @@ impl AsyncWrite for ReplayTransport {
         Poll::Ready(Ok(()))
     }
 }
+
+/// Replays a session [recording](super::recording), optionally with its original timing
+pub struct RecordingReplayTransport {
+    reader: RecordingReader<BufReader<fs::File>>,
+    on_complete: Option<oneshot::Sender<()>>,
+    speed: Option<f64>,
+    seek: Duration,
+    started: Option<Instant>,
+
+    /// The next event to replay, and when. This is only taken once it's time to
+    /// replay it, so a [Transport::read] that's cancelled while waiting doesn't
+    /// lose it.
+    pending: Option<(Duration, TransportEvent)>,
+}
+
+impl RecordingReplayTransport {
+    /// Returns true if the file at `path` is a recording (rather than a legacy
+    /// dump of raw bytes)
+    pub async fn is_recording(path: &Path) -> io::Result<bool> {
+        let mut header = [0u8; MAGIC.len()];
+        let mut file = fs::File::open(path).await?;
+        match file.read_exact(&mut header).await {
+            Ok(_) => Ok(&header == MAGIC),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn for_replay(config: ReplayConfig) -> io::Result<Self> {
+        let mut file = BufReader::new(fs::File::open(config.path).await?);
+        let mut header = [0u8; MAGIC.len()];
+        file.read_exact(&mut header).await?;
+
+        Ok(Self {
+            reader: RecordingReader::new(file),
+            on_complete: Some(config.on_complete),
+            speed: config.speed,
+            seek: config.seek,
+            started: None,
+            pending: None,
+        })
+    }
+
+    /// Wait until it's time to replay something that happened at `elapsed`
+    async fn wait_for(&mut self, elapsed: Duration) {
+        let Some(speed) = self.speed else {
+            return;
+        };
+        let Some(offset) = elapsed.checked_sub(self.seek) else {
+            // Still seeking
+            return;
+        };
+
+        let started = *self.started.get_or_insert_with(Instant::now);
+        time::sleep_until(started + offset.div_f64(speed)).await;
+    }
+
+    /// Read the next inbound event from the recording, along with when it happened
+    async fn read_next(&mut self) -> io::Result<(Duration, TransportEvent)> {
+        loop {
+            let entry = match self.reader.next().await? {
+                Some(entry) => entry,
+                None => {
+                    if let Some(on_complete) = self.on_complete.take() {
+                        let _ = on_complete.send(());
+                    }
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            };
+
+            let event = match entry.record {
+                Record::InboundData(data) => TransportEvent::Data(data),
+                Record::InboundEvent(event) => event.into_transport(),
+
+                // We're not actually talking to anybody
+                Record::OutboundData(_) | Record::OutboundEvent(_) => continue,
+            };
+
+            return Ok((entry.elapsed, event));
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for RecordingReplayTransport {
+    async fn read(&mut self) -> io::Result<TransportEvent> {
+        let elapsed = match &self.pending {
+            Some((elapsed, _)) => *elapsed,
+            None => {
+                let (elapsed, event) = self.read_next().await?;
+                self.pending = Some((elapsed, event));
+                elapsed
+            }
+        };
+
+        self.wait_for(elapsed).await;
+
+        let (_, event) = self.pending.take().expect("No pending event after waiting");
+        Ok(event)
+    }
+
+    async fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        Ok(data.len())
+    }
+
+    async fn notify(&mut self, _notification: TransportNotification) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn send_event(&mut self, _event: EventData) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn send_keepalive(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use assert_matches::assert_matches;
+    use bytes::Bytes;
+
+    use super::*;
+
+    fn record(elapsed_ms: u64, text: &str) -> Vec<u8> {
+        let mut record = vec![b'<'];
+        record.extend_from_slice(&elapsed_ms.to_le_bytes());
+        record.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        record.extend_from_slice(text.as_bytes());
+        record
+    }
+
+    async fn transport_for(
+        name: &str,
+        speed: Option<f64>,
+        seek: Duration,
+    ) -> io::Result<(RecordingReplayTransport, PathBuf)> {
+        let path = std::env::temp_dir().join(format!("kodachi-{name}-{}", std::process::id()));
+        let mut contents = MAGIC.to_vec();
+        contents.extend(record(0, "first"));
+        contents.extend(record(1000, "second"));
+        contents.extend(record(3000, "third"));
+        fs::write(&path, contents).await?;
+
+        let (on_complete, _) = oneshot::channel();
+        let transport = RecordingReplayTransport::for_replay(ReplayConfig {
+            path: path.clone(),
+            on_complete,
+            speed,
+            seek,
+        })
+        .await?;
+        Ok((transport, path))
+    }
+
+    /// Read every remaining event, along with how long after `start` it was read
+    async fn read_all(
+        transport: &mut RecordingReplayTransport,
+        start: Instant,
+    ) -> Vec<(Duration, Bytes)> {
+        let mut events = Vec::new();
+        while let Ok(event) = transport.read().await {
+            let TransportEvent::Data(data) = event else {
+                panic!("Unexpected event: {event:?}");
+            };
+            events.push((start.elapsed(), data));
+        }
+        events
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timing_test() -> io::Result<()> {
+        let (mut transport, path) = transport_for("timing", Some(2.0), Duration::ZERO).await?;
+        let events = read_all(&mut transport, Instant::now()).await;
+        fs::remove_file(path).await?;
+
+        assert_eq!(
+            events,
+            vec![
+                (Duration::ZERO, Bytes::from_static(b"first")),
+                (Duration::from_millis(500), Bytes::from_static(b"second")),
+                (Duration::from_millis(1500), Bytes::from_static(b"third")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn seek_test() -> io::Result<()> {
+        let (mut transport, path) =
+            transport_for("seek", Some(1.0), Duration::from_millis(2000)).await?;
+        let events = read_all(&mut transport, Instant::now()).await;
+        fs::remove_file(path).await?;
+
+        // Everything before the seek point is replayed immediately, and timing
+        // resumes from there:
+        assert_eq!(
+            events,
+            vec![
+                (Duration::ZERO, Bytes::from_static(b"first")),
+                (Duration::ZERO, Bytes::from_static(b"second")),
+                (Duration::from_millis(1000), Bytes::from_static(b"third")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelled_read_test() -> io::Result<()> {
+        let (mut transport, path) = transport_for("cancelled", Some(1.0), Duration::ZERO).await?;
+        let start = Instant::now();
+        assert_matches!(transport.read().await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"first");
+        });
+
+        // Give up waiting for "second" partway through (as if some other branch of
+        // a select! completed first)...
+        let cancelled = time::timeout(Duration::from_millis(500), transport.read()).await;
+        assert!(cancelled.is_err());
+
+        // ... It should still be replayed, on time:
+        let events = read_all(&mut transport, start).await;
+        fs::remove_file(path).await?;
+
+        assert_eq!(
+            events,
+            vec![
+                (Duration::from_millis(1000), Bytes::from_static(b"second")),
+                (Duration::from_millis(3000), Bytes::from_static(b"third")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn as_fast_as_possible_test() -> io::Result<()> {
+        let (mut transport, path) = transport_for("fast", None, Duration::ZERO).await?;
+        let events = read_all(&mut transport, Instant::now()).await;
+        fs::remove_file(path).await?;
+
+        assert_eq!(
+            events.iter().map(|(at, _)| *at).collect::<Vec<_>>(),
+            vec![Duration::ZERO; 3]
+        );
+        Ok(())
+    }
+}
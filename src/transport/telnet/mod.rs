@@ -1,24 +1,29 @@
-use std::io;
+use std::{collections::VecDeque, io};
 
 use async_trait::async_trait;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use log::trace;
 use protocol::TelnetCommand;
 use tokio::{
     io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::TcpStream,
-    select,
+    time,
 };
-use tokio_native_tls::{TlsConnector, TlsStream};
+use tokio_native_tls::TlsStream;
 
 use self::{
     options::{mccp::CompressableStream, TelnetOptionsManager},
-    protocol::TelnetOption,
+    protocol::{NegotiationType, TelnetOption},
 };
 
-use super::{Transport, TransportEvent, TransportNotification};
+use crate::net::tls::TlsOptions;
 
-mod options;
+use super::{
+    connect::{connect_tcp, connect_tls, ConnectOptions},
+    EventData, Transport, TransportEvent, TransportNotification,
+};
+
+pub(crate) mod options;
 mod processor;
 mod protocol;
 
@@ -29,28 +34,40 @@ pub struct TelnetTransport<S: AsyncRead + AsyncWrite> {
     stream: CompressableStream<S>,
     telnet: TelnetProcessor,
     options: TelnetOptionsManager,
+
+    /// Events emitted by option handlers, which must be yielded before we
+    /// process any more of `buffer`, since they may affect how it's handled
+    /// (IE: a new encoding)
+    pending_events: VecDeque<TransportEvent>,
 }
 
 impl TelnetTransport<TcpStream> {
-    pub async fn connect(host: &str, port: u16, buffer_size: usize) -> io::Result<Self> {
-        Self::connect_with_stream(TcpStream::connect((host, port)).await?, buffer_size).await
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        buffer_size: usize,
+        options: &mut ConnectOptions<'_>,
+    ) -> io::Result<Self> {
+        let timeout = options.timeout;
+        let stream = time::timeout(timeout, connect_tcp(host, port, options))
+            .await
+            .map_err(|_| options.timed_out())??;
+        Self::connect_with_stream(stream, buffer_size).await
     }
 }
 
 impl TelnetTransport<TlsStream<TcpStream>> {
-    pub async fn connect_tls(host: &str, port: u16, buffer_size: usize) -> io::Result<Self> {
-        let tcp = TcpStream::connect((host, port)).await?;
-        let connector = match native_tls::TlsConnector::builder().build() {
-            Ok(connector) => connector,
-            Err(err) => return Err(io::Error::other(err)),
-        };
-        let cx = TlsConnector::from(connector);
-
-        let stream = match cx.connect(host, tcp).await {
-            Ok(stream) => stream,
-            Err(err) => return Err(io::Error::other(err)),
-        };
-
+    pub async fn connect_tls(
+        host: &str,
+        port: u16,
+        tls: &TlsOptions,
+        buffer_size: usize,
+        options: &mut ConnectOptions<'_>,
+    ) -> io::Result<Self> {
+        let timeout = options.timeout;
+        let stream = time::timeout(timeout, connect_tls(host, port, tls, options))
+            .await
+            .map_err(|_| options.timed_out())??;
         Self::connect_with_stream(stream, buffer_size).await
     }
 }
@@ -60,25 +77,61 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send> TelnetTransport<S> {
         let buffer = BytesMut::with_capacity(buffer_size);
         let mut options = TelnetOptionsManager::default();
         options.on_connected(&mut stream).await?;
+        stream.flush().await?;
 
         Ok(Self {
             buffer,
             stream: CompressableStream::new(stream),
             telnet: TelnetProcessor::default(),
             options,
+            pending_events: VecDeque::new(),
         })
     }
 
+    async fn negotiate_compression(&mut self, negotiation: NegotiationType) -> io::Result<()> {
+        let accepted = self.options.is_accepted(TelnetOption::MCCP3);
+        match negotiation {
+            NegotiationType::Will if accepted && !self.stream.is_compressing() => {
+                // Everything we send after this subnegotiation must be compressed
+                TelnetEvent::Subnegotiate(TelnetOption::MCCP3, Bytes::new())
+                    .write_all(&mut self.stream)
+                    .await?;
+                self.stream.start_compressing();
+            }
+
+            NegotiationType::Wont | NegotiationType::Dont if self.stream.is_compressing() => {
+                self.stream.stop_compressing()?;
+            }
+
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn next_pending_event(&mut self) -> TransportEvent {
+        self.pending_events
+            .pop_front()
+            .unwrap_or(TransportEvent::Nop)
+    }
+
     async fn process_buffer(&mut self) -> io::Result<Option<TransportEvent>> {
         match self.telnet.process_one(&mut self.buffer)? {
             Some(TelnetEvent::Data(bytes)) => Ok(Some(TransportEvent::Data(bytes))),
             Some(TelnetEvent::Negotiate(negotiation, option)) => {
                 trace!(target: "telnet", "<< {:?} {:?}", negotiation, option);
 
-                self.options
+                let events = self
+                    .options
                     .negotiate(negotiation, option, &mut self.stream)
                     .await?;
-                Ok(Some(TransportEvent::Nop))
+                self.pending_events.extend(events);
+
+                if option == TelnetOption::MCCP3 {
+                    self.negotiate_compression(negotiation).await?;
+                }
+
+                self.stream.flush().await?;
+                Ok(Some(self.next_pending_event()))
             }
             Some(TelnetEvent::Subnegotiate(option, data)) => {
                 trace!(target: "telnet", "<< SB {:?} {:?} SE", option, data);
@@ -93,18 +146,26 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send> TelnetTransport<S> {
 
                     // Otherwise, delgate to the options manager
                     _ => {
-                        self.options
+                        let events = self
+                            .options
                             .subnegotiate(option, data, &mut self.stream)
                             .await?;
+                        self.pending_events.extend(events);
+                        self.stream.flush().await?;
                     }
                 }
 
-                Ok(Some(TransportEvent::Nop))
+                Ok(Some(self.next_pending_event()))
             }
             Some(TelnetEvent::Command(TelnetCommand::EOR)) => {
                 // The server has told us there's a prompt here
                 Ok(Some(TransportEvent::EndOfPrompt))
             }
+            Some(TelnetEvent::Command(TelnetCommand::GoAhead))
+                if !self.options.is_accepted(TelnetOption::SuppressGoAhead) =>
+            {
+                Ok(Some(TransportEvent::GoAhead))
+            }
             Some(_) => {
                 // TODO: Log unexpected event?
                 Ok(Some(TransportEvent::Nop))
@@ -118,6 +179,10 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send> TelnetTransport<S> {
 impl<S: AsyncRead + AsyncWrite + Unpin + Send> Transport for TelnetTransport<S> {
     async fn read(&mut self) -> io::Result<TransportEvent> {
         loop {
+            if let Some(pending) = self.pending_events.pop_front() {
+                return Ok(pending);
+            }
+
             match self.process_buffer().await? {
                 None => {
                     // Nothing to do (right now); proceed with read below
@@ -131,33 +196,214 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send> Transport for TelnetTransport<S>
             }
         }
 
-        select! {
-            read = self.stream.read_buf(&mut self.buffer) => {
-                if read? == 0 && self.buffer.is_empty() {
-                    return Err(io::ErrorKind::UnexpectedEof.into());
-                }
-
-                self.process_buffer()
-                    .await
-                    .map(|option| option.unwrap_or(TransportEvent::Nop))
-            },
-
-            event = self.options.recv_event() => {
-                if let Some(event) = event {
-                    Ok(TransportEvent::Event(event))
-                } else {
-                    Ok(TransportEvent::Nop)
-                }
-            },
+        let read = self.stream.read_buf(&mut self.buffer).await?;
+        if read == 0 && self.buffer.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
         }
+
+        self.process_buffer()
+            .await
+            .map(|option| option.unwrap_or(TransportEvent::Nop))
     }
 
     async fn write(&mut self, data: &[u8]) -> io::Result<usize> {
-        self.stream.write_all(data).await?;
+        if data.contains(&protocol::IAC) {
+            // Literal IAC bytes (IE: from non-UTF-8 encodings) must be doubled, or
+            // the server will interpret them as commands
+            let mut escaped = Vec::with_capacity(data.len() + 1);
+            for &byte in data {
+                if byte == protocol::IAC {
+                    escaped.push(protocol::IAC);
+                }
+                escaped.push(byte);
+            }
+            self.stream.write_all(&escaped).await?;
+        } else {
+            self.stream.write_all(data).await?;
+        }
+        self.stream.flush().await?;
         Ok(data.len())
     }
 
     async fn notify(&mut self, notification: TransportNotification) -> io::Result<()> {
-        self.options.notify(notification, &mut self.stream).await
+        self.options.notify(notification, &mut self.stream).await?;
+        self.stream.flush().await
+    }
+
+    async fn send_event(&mut self, event: EventData) -> io::Result<()> {
+        self.options.send_event(event, &mut self.stream).await?;
+        self.stream.flush().await
+    }
+
+    async fn send_keepalive(&mut self) -> io::Result<()> {
+        TelnetEvent::Command(TelnetCommand::Nop)
+            .write_all(&mut self.stream)
+            .await?;
+        self.stream.flush().await
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        // NOTE: This also cleanly terminates any outbound compression
+        self.stream.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use crate::net::encoding::TextEncoding;
+
+    use super::*;
+
+    /// Read the next event that isn't a [TransportEvent::Nop]
+    async fn read_event<S: AsyncRead + AsyncWrite + Unpin + Send>(
+        transport: &mut TelnetTransport<S>,
+    ) -> io::Result<TransportEvent> {
+        loop {
+            match transport.read().await? {
+                TransportEvent::Nop => {}
+                event => return Ok(event),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn go_ahead_as_end_of_prompt_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut transport = TelnetTransport::connect_with_stream(local, 4096).await?;
+
+        remote.write_all(b"HP: 42> \xFF\xF9").await?;
+
+        assert_matches!(transport.read().await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"HP: 42> ");
+        });
+        assert_matches!(transport.read().await?, TransportEvent::GoAhead);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn suppressed_go_ahead_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut transport = TelnetTransport::connect_with_stream(local, 4096).await?;
+
+        // Once the server has offered to suppress Go Ahead, any it sends anyway are
+        // not meaningful as prompt markers
+        remote
+            .write_all(b"\xFF\xFB\x03HP: 42> \xFF\xF9\r\n")
+            .await?;
+
+        assert_matches!(read_event(&mut transport).await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"HP: 42> ");
+        });
+        assert_matches!(read_event(&mut transport).await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"\r\n");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn escape_iac_on_write_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut transport = TelnetTransport::connect_with_stream(local, 4096).await?;
+
+        let latin1 = TextEncoding::for_label("latin1").unwrap();
+        let data = latin1.encode("\u{ff}\r\n");
+        assert_eq!(&data[..], b"\xFF\r\n");
+        assert_eq!(transport.write(&data).await?, data.len());
+        drop(transport);
+
+        let mut written = Vec::new();
+        remote.read_to_end(&mut written).await?;
+        assert!(
+            written.ends_with(b"\xFF\xFF\r\n"),
+            "Expected escaped IAC in {written:?}"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encoding_changed_before_data_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut transport = TelnetTransport::connect_with_stream(local, 4096).await?;
+
+        // NOTE: Everything arrives at once; the new encoding must be yielded before
+        // the text that follows it so that text can be decoded correctly
+        remote
+            .write_all(b"\xFF\xFD\x2A\xFF\xFA\x2A\x02ISO-8859-1\xFF\xF0caf\xE9\r\n")
+            .await?;
+
+        let latin1 = TextEncoding::for_label("ISO-8859-1").unwrap();
+        assert_matches!(read_event(&mut transport).await?, TransportEvent::EncodingChanged(encoding) => {
+            assert_eq!(encoding, latin1);
+        });
+        assert_matches!(read_event(&mut transport).await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"caf\xE9\r\n");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn accept_offered_charset_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut transport = TelnetTransport::connect_with_stream(local, 4096).await?;
+
+        remote
+            .write_all(b"\xFF\xFB\x2A\xFF\xFA\x2A\x01;ISO-8859-1;UTF-8\xFF\xF0")
+            .await?;
+
+        assert_matches!(
+            read_event(&mut transport).await?,
+            TransportEvent::EncodingChanged(TextEncoding::Utf8)
+        );
+
+        let expected = b"\xFF\xFD\x2A\xFF\xFA\x2A\x02UTF-8\xFF\xF0";
+        let mut response = vec![0; expected.len()];
+        remote.read_exact(&mut response).await?;
+        assert_eq!(&response[..], expected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn server_echo_before_prompt_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut transport = TelnetTransport::connect_with_stream(local, 4096).await?;
+
+        remote.write_all(b"\xFF\xFB\x01Password: ").await?;
+
+        assert_matches!(
+            read_event(&mut transport).await?,
+            TransportEvent::ServerEcho { enabled: true }
+        );
+        assert_matches!(read_event(&mut transport).await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"Password: ");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mxp_enabled_before_tagged_text_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut transport = TelnetTransport::connect_with_stream(local, 4096).await?;
+
+        remote
+            .write_all(b"\xFF\xFB\x5B\xFF\xFA\x5B\xFF\xF0<b>Exits</b>\r\n")
+            .await?;
+
+        assert_matches!(
+            read_event(&mut transport).await?,
+            TransportEvent::Mxp { enabled: true }
+        );
+        assert_matches!(read_event(&mut transport).await?, TransportEvent::Data(data) => {
+            assert_eq!(&data[..], b"<b>Exits</b>\r\n");
+        });
+
+        Ok(())
     }
 }
@@ -0,0 +1,243 @@
+use std::io;
+
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use tokio::sync::broadcast::Sender;
+
+use crate::{
+    net::{encoding::TextEncoding, writable::ObjectWriteStream},
+    transport::{
+        telnet::{
+            processor::TelnetEvent,
+            protocol::{NegotiationType, TelnetOption},
+        },
+        TransportEvent,
+    },
+};
+
+use super::{negotiator::OptionsNegotiatorBuilder, DynWriteStream, TelnetOptionHandler};
+
+const REQUEST: u8 = 1;
+const ACCEPTED: u8 = 2;
+const REJECTED: u8 = 3;
+const TTABLE_IS: u8 = 4;
+const TTABLE_REJECTED: u8 = 5;
+
+const TTABLE_PREFIX: &[u8] = b"[TTABLE]";
+
+const PREFERRED_CHARSET: &str = "UTF-8";
+
+/// Implements RFC 2066 CHARSET negotiation. Either side may offer CHARSET; if the
+/// server asks us to (DO), we request UTF-8, and if the server offers (WILL), we
+/// wait for its REQUEST. Either way, we prefer UTF-8 but will accept any
+/// (ASCII-compatible) charset we know how to decode if the server offers it
+/// instead.
+pub struct CharsetOptionHandler {
+    events: Sender<TransportEvent>,
+}
+
+impl CharsetOptionHandler {
+    pub fn new(sender: Sender<TransportEvent>) -> Self {
+        CharsetOptionHandler { events: sender }
+    }
+
+    async fn send(
+        &self,
+        stream: &mut DynWriteStream<'_>,
+        command: u8,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut response = BytesMut::default();
+        response.put_u8(command);
+        response.put_slice(data);
+
+        stream
+            .write_object(TelnetEvent::Subnegotiate(
+                TelnetOption::Charset,
+                response.freeze(),
+            ))
+            .await
+    }
+
+    fn set_encoding(&self, encoding: TextEncoding) {
+        log::trace!(target: "telnet", "charset: {encoding:?}");
+        self.events
+            .send(TransportEvent::EncodingChanged(encoding))
+            .ok();
+    }
+
+    async fn on_request(&self, mut data: &[u8], mut stream: DynWriteStream<'_>) -> io::Result<()> {
+        if data.starts_with(TTABLE_PREFIX) {
+            // Skip the TTABLE version byte; we don't support translation tables,
+            // but may still be able to use one of the offered charsets
+            data = data.get(TTABLE_PREFIX.len() + 1..).unwrap_or_default();
+        }
+
+        let Some((separator, charsets)) = data.split_first() else {
+            return self.send(&mut stream, REJECTED, b"").await;
+        };
+
+        let offered: Vec<(&[u8], TextEncoding)> = charsets
+            .split(|b| b == separator)
+            .filter_map(|name| {
+                let encoding = TextEncoding::for_label(std::str::from_utf8(name).ok()?)?;
+                Some((name, encoding))
+            })
+            .collect();
+        log::trace!(target: "telnet", "<< CHARSET REQUEST {:?}", String::from_utf8_lossy(charsets));
+
+        let accepted = offered
+            .iter()
+            .find(|(_, encoding)| *encoding == TextEncoding::Utf8)
+            .or_else(|| offered.first());
+
+        if let Some((name, encoding)) = accepted {
+            self.send(&mut stream, ACCEPTED, name).await?;
+            self.set_encoding(*encoding);
+        } else {
+            self.send(&mut stream, REJECTED, b"").await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TelnetOptionHandler for CharsetOptionHandler {
+    fn option(&self) -> TelnetOption {
+        TelnetOption::Charset
+    }
+
+    fn register(&self, negotiator: OptionsNegotiatorBuilder) -> OptionsNegotiatorBuilder {
+        negotiator
+            .accept_do(TelnetOption::Charset)
+            .accept_will(TelnetOption::Charset)
+    }
+
+    async fn negotiate(
+        &mut self,
+        negotiation: NegotiationType,
+        mut stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        if negotiation == NegotiationType::Do {
+            let mut request = vec![b';'];
+            request.extend_from_slice(PREFERRED_CHARSET.as_bytes());
+            self.send(&mut stream, REQUEST, &request).await?;
+        }
+        Ok(())
+    }
+
+    async fn subnegotiate(
+        &mut self,
+        data: Bytes,
+        mut stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        let Some((command, data)) = data.split_first() else {
+            return Ok(());
+        };
+
+        match *command {
+            REQUEST => self.on_request(data, stream).await?,
+
+            ACCEPTED => {
+                let name = String::from_utf8_lossy(data);
+                if let Some(encoding) = TextEncoding::for_label(&name) {
+                    self.set_encoding(encoding);
+                } else {
+                    log::trace!(target: "telnet", "<< CHARSET ACCEPTED unknown: {name}");
+                }
+            }
+
+            REJECTED => {
+                log::trace!(target: "telnet", "<< CHARSET REJECTED");
+            }
+
+            TTABLE_IS => {
+                self.send(&mut stream, TTABLE_REJECTED, b"").await?;
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use tokio::sync::broadcast;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn request_utf8_test() -> io::Result<()> {
+        let (sender, _receiver) = broadcast::channel(8);
+        let mut handler = CharsetOptionHandler::new(sender);
+        let mut output: Vec<u8> = Vec::default();
+
+        handler
+            .negotiate(NegotiationType::Do, Box::new(&mut output))
+            .await?;
+
+        assert_eq!(output, b"\xff\xfa\x2a\x01;UTF-8\xff\xf0");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn accept_preferred_charset_test() -> io::Result<()> {
+        let (sender, mut receiver) = broadcast::channel(8);
+        let mut handler = CharsetOptionHandler::new(sender);
+        let mut output: Vec<u8> = Vec::default();
+
+        handler
+            .subnegotiate(
+                Bytes::from_static(b"\x01 ISO-8859-1 UTF-8 klingon"),
+                Box::new(&mut output),
+            )
+            .await?;
+
+        assert_eq!(output, b"\xff\xfa\x2a\x02UTF-8\xff\xf0");
+        assert_matches!(
+            receiver.try_recv(),
+            Ok(TransportEvent::EncodingChanged(TextEncoding::Utf8))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn accept_fallback_charset_test() -> io::Result<()> {
+        let (sender, mut receiver) = broadcast::channel(8);
+        let mut handler = CharsetOptionHandler::new(sender);
+        let mut output: Vec<u8> = Vec::default();
+
+        handler
+            .subnegotiate(
+                Bytes::from_static(b"\x01;klingon;CP437"),
+                Box::new(&mut output),
+            )
+            .await?;
+
+        assert_eq!(output, b"\xff\xfa\x2a\x02CP437\xff\xf0");
+        assert_matches!(
+            receiver.try_recv(),
+            Ok(TransportEvent::EncodingChanged(TextEncoding::Cp437))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reject_unknown_charsets_test() -> io::Result<()> {
+        let (sender, mut receiver) = broadcast::channel(8);
+        let mut handler = CharsetOptionHandler::new(sender);
+        let mut output: Vec<u8> = Vec::default();
+
+        handler
+            .subnegotiate(Bytes::from_static(b"\x01;klingon"), Box::new(&mut output))
+            .await?;
+
+        assert_eq!(output, b"\xff\xfa\x2a\x03\xff\xf0");
+        assert!(receiver.try_recv().is_err());
+        Ok(())
+    }
+}
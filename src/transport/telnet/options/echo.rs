@@ -0,0 +1,99 @@
+use std::io;
+
+use async_trait::async_trait;
+use tokio::sync::broadcast::Sender;
+
+use crate::transport::{
+    telnet::protocol::{NegotiationType, TelnetOption},
+    TransportEvent,
+};
+
+use super::{negotiator::OptionsNegotiatorBuilder, DynWriteStream, TelnetOptionHandler};
+
+/// Tracks whether the server is echoing our input. Servers generally do this (without
+/// actually echoing anything) while prompting for a password.
+pub struct EchoOptionHandler {
+    events: Sender<TransportEvent>,
+    server_echo: bool,
+}
+
+impl EchoOptionHandler {
+    pub fn new(sender: Sender<TransportEvent>) -> Self {
+        EchoOptionHandler {
+            events: sender,
+            server_echo: false,
+        }
+    }
+
+    fn set_server_echo(&mut self, enabled: bool) {
+        if self.server_echo != enabled {
+            self.server_echo = enabled;
+
+            log::trace!(target: "telnet", "server echo: {enabled}");
+            self.events
+                .send(TransportEvent::ServerEcho { enabled })
+                .ok();
+        }
+    }
+}
+
+#[async_trait]
+impl TelnetOptionHandler for EchoOptionHandler {
+    fn option(&self) -> TelnetOption {
+        TelnetOption::Echo
+    }
+
+    fn register(&self, negotiator: OptionsNegotiatorBuilder) -> OptionsNegotiatorBuilder {
+        negotiator.accept_will(TelnetOption::Echo)
+    }
+
+    async fn negotiate(
+        &mut self,
+        negotiation: NegotiationType,
+        _stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        match negotiation {
+            NegotiationType::Will => self.set_server_echo(true),
+            NegotiationType::Wont => self.set_server_echo(false),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use tokio::sync::broadcast;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn notify_on_change_test() -> io::Result<()> {
+        let (sender, mut receiver) = broadcast::channel(8);
+        let mut handler = EchoOptionHandler::new(sender);
+        let mut sink = tokio::io::sink();
+
+        handler
+            .negotiate(NegotiationType::Will, Box::new(&mut sink))
+            .await?;
+        handler
+            .negotiate(NegotiationType::Will, Box::new(&mut sink))
+            .await?;
+        handler
+            .negotiate(NegotiationType::Wont, Box::new(&mut sink))
+            .await?;
+
+        assert_matches!(
+            receiver.try_recv(),
+            Ok(TransportEvent::ServerEcho { enabled: true })
+        );
+        assert_matches!(
+            receiver.try_recv(),
+            Ok(TransportEvent::ServerEcho { enabled: false })
+        );
+        assert!(receiver.try_recv().is_err());
+
+        Ok(())
+    }
+}
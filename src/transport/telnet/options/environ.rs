@@ -0,0 +1,339 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, io,
+};
+
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    net::writable::ObjectWriteStream,
+    transport::{
+        telnet::{
+            processor::TelnetEvent,
+            protocol::{NegotiationType, TelnetOption},
+        },
+        TransportNotification,
+    },
+};
+
+use super::{
+    negotiator::OptionsNegotiatorBuilder, ttype::mtts_bit_vector, DynWriteStream,
+    TelnetOptionHandler,
+};
+
+const IS: u8 = 0;
+const SEND: u8 = 1;
+const INFO: u8 = 2;
+
+const VAR: u8 = 0;
+const VALUE: u8 = 1;
+const ESC: u8 = 2;
+const USERVAR: u8 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VarType {
+    Var,
+    UserVar,
+}
+
+impl VarType {
+    fn as_byte(&self) -> u8 {
+        match self {
+            VarType::Var => VAR,
+            VarType::UserVar => USERVAR,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Requested {
+    Nothing,
+    All,
+    Some(Vec<(VarType, String)>),
+}
+
+fn put_escaped(buf: &mut BytesMut, value: &[u8]) {
+    for byte in value {
+        if matches!(*byte, VAR | VALUE | ESC | USERVAR) {
+            buf.put_u8(ESC);
+        }
+        buf.put_u8(*byte);
+    }
+}
+
+fn parse_send(data: &[u8]) -> Vec<(VarType, String)> {
+    let mut requested: Vec<(VarType, Vec<u8>)> = Vec::new();
+    let mut bytes = data.iter();
+    while let Some(byte) = bytes.next() {
+        match *byte {
+            VAR => requested.push((VarType::Var, Vec::new())),
+            USERVAR => requested.push((VarType::UserVar, Vec::new())),
+            ESC => {
+                if let (Some(escaped), Some((_, name))) = (bytes.next(), requested.last_mut()) {
+                    name.push(*escaped);
+                }
+            }
+            other => {
+                if let Some((_, name)) = requested.last_mut() {
+                    name.push(other);
+                }
+            }
+        }
+    }
+
+    requested
+        .into_iter()
+        .map(|(var_type, name)| (var_type, String::from_utf8_lossy(&name).to_string()))
+        .collect()
+}
+
+/// Answers NEW-ENVIRON requests, primarily for MNES (Mud New-Environ Standard)
+/// variables. Clients may add to (or override) the default variables via
+/// [TransportNotification::Environment].
+pub struct NewEnvironOptionHandler {
+    variables: BTreeMap<String, String>,
+    requested: Requested,
+}
+
+impl Default for NewEnvironOptionHandler {
+    fn default() -> Self {
+        let mut variables = BTreeMap::new();
+        variables.insert("CLIENT_NAME".to_string(), "kodachi".to_string());
+        variables.insert(
+            "CLIENT_VERSION".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        );
+        variables.insert("CHARSET".to_string(), "UTF-8".to_string());
+        variables.insert("MTTS".to_string(), mtts_bit_vector().to_string());
+        if let Ok(term) = env::var("TERM") {
+            variables.insert("TERMINAL_TYPE".to_string(), term);
+        }
+
+        Self {
+            variables,
+            requested: Requested::Nothing,
+        }
+    }
+}
+
+impl NewEnvironOptionHandler {
+    fn put_variable(&self, buf: &mut BytesMut, var_type: VarType, name: &str) {
+        buf.put_u8(var_type.as_byte());
+        put_escaped(buf, name.as_bytes());
+
+        // NOTE: Unknown variables are sent without a VALUE to indicate that they're undefined
+        if let Some(value) = self.variables.get(name) {
+            buf.put_u8(VALUE);
+            put_escaped(buf, value.as_bytes());
+        }
+    }
+
+    fn is_requested(&self, name: &str) -> bool {
+        match &self.requested {
+            Requested::Nothing => false,
+            Requested::All => true,
+            Requested::Some(requested) => requested.iter().any(|(_, n)| n == name),
+        }
+    }
+
+    async fn send(&self, stream: &mut DynWriteStream<'_>, response: BytesMut) -> io::Result<()> {
+        stream
+            .write_object(TelnetEvent::Subnegotiate(
+                TelnetOption::NewEnviron,
+                response.freeze(),
+            ))
+            .await
+    }
+
+    async fn on_send(&mut self, data: &[u8], mut stream: DynWriteStream<'_>) -> io::Result<()> {
+        let requested = parse_send(data);
+        log::trace!(target: "telnet", "<< NEW-ENVIRON SEND {:?}", requested);
+
+        let mut response = BytesMut::default();
+        response.put_u8(IS);
+
+        // An empty list (or one without names) means "send everything"
+        if requested.iter().all(|(_, name)| name.is_empty()) {
+            for name in self.variables.keys() {
+                self.put_variable(&mut response, VarType::Var, name);
+            }
+            self.requested = Requested::All;
+        } else {
+            for (var_type, name) in &requested {
+                self.put_variable(&mut response, *var_type, name);
+            }
+
+            match &mut self.requested {
+                Requested::All => {}
+                Requested::Some(existing) => existing.extend(requested),
+                Requested::Nothing => self.requested = Requested::Some(requested),
+            }
+        }
+
+        self.send(&mut stream, response).await
+    }
+
+    async fn update_variables(
+        &mut self,
+        variables: &HashMap<String, String>,
+        mut stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        let mut info = BytesMut::default();
+        info.put_u8(INFO);
+
+        let mut changed = false;
+        for (name, value) in variables {
+            if self.variables.get(name) == Some(value) {
+                continue;
+            }
+
+            self.variables.insert(name.to_string(), value.to_string());
+
+            // MNES says we should let the server know about changes to any
+            // variables it has asked about
+            if self.is_requested(name) {
+                self.put_variable(&mut info, VarType::Var, name);
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.send(&mut stream, info).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TelnetOptionHandler for NewEnvironOptionHandler {
+    fn option(&self) -> TelnetOption {
+        TelnetOption::NewEnviron
+    }
+
+    fn register(&self, negotiator: OptionsNegotiatorBuilder) -> OptionsNegotiatorBuilder {
+        negotiator.accept_do(TelnetOption::NewEnviron)
+    }
+
+    async fn negotiate(
+        &mut self,
+        negotiation: NegotiationType,
+        _stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        if negotiation == NegotiationType::Dont {
+            self.requested = Requested::Nothing;
+        }
+        Ok(())
+    }
+
+    async fn notify(
+        &mut self,
+        notification: &TransportNotification,
+        stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        if let TransportNotification::Environment(variables) = notification {
+            self.update_variables(variables, stream).await?;
+        }
+        Ok(())
+    }
+
+    async fn subnegotiate(&mut self, data: Bytes, stream: DynWriteStream<'_>) -> io::Result<()> {
+        match data.split_first() {
+            Some((&SEND, requested)) => self.on_send(requested, stream).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handler_with(variables: &[(&str, &str)]) -> NewEnvironOptionHandler {
+        NewEnvironOptionHandler {
+            variables: variables
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            requested: Requested::Nothing,
+        }
+    }
+
+    #[tokio::test]
+    async fn send_requested_test() -> io::Result<()> {
+        let mut handler = handler_with(&[("CLIENT_NAME", "kodachi"), ("CHARSET", "UTF-8")]);
+        let mut output: Vec<u8> = Vec::default();
+
+        handler
+            .subnegotiate(
+                Bytes::from_static(b"\x01\x00CLIENT_NAME\x00IPADDRESS"),
+                Box::new(&mut output),
+            )
+            .await?;
+
+        assert_eq!(
+            output,
+            b"\xff\xfa\x27\x00\x00CLIENT_NAME\x01kodachi\x00IPADDRESS\xff\xf0"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_all_test() -> io::Result<()> {
+        let mut handler = handler_with(&[("CLIENT_NAME", "kodachi"), ("CHARSET", "UTF-8")]);
+        let mut output: Vec<u8> = Vec::default();
+
+        handler
+            .subnegotiate(Bytes::from_static(b"\x01"), Box::new(&mut output))
+            .await?;
+
+        assert_eq!(
+            output,
+            b"\xff\xfa\x27\x00\x00CHARSET\x01UTF-8\x00CLIENT_NAME\x01kodachi\xff\xf0"
+        );
+        assert_eq!(handler.requested, Requested::All);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn info_on_change_test() -> io::Result<()> {
+        let mut handler = handler_with(&[("CLIENT_NAME", "kodachi"), ("CHARSET", "UTF-8")]);
+        let mut output: Vec<u8> = Vec::default();
+
+        handler
+            .subnegotiate(
+                Bytes::from_static(b"\x01\x00CHARSET"),
+                Box::new(&mut output),
+            )
+            .await?;
+        output.clear();
+
+        let mut variables = HashMap::new();
+        variables.insert("CHARSET".to_string(), "ASCII".to_string());
+        variables.insert("CLIENT_NAME".to_string(), "kodachi".to_string());
+        variables.insert("IPADDRESS".to_string(), "127.0.0.1".to_string());
+        handler
+            .notify(
+                &TransportNotification::Environment(variables),
+                Box::new(&mut output),
+            )
+            .await?;
+
+        assert_eq!(output, b"\xff\xfa\x27\x02\x00CHARSET\x01ASCII\xff\xf0");
+        assert_eq!(handler.variables["IPADDRESS"], "127.0.0.1");
+        Ok(())
+    }
+
+    #[test]
+    fn parse_escaped_test() {
+        let requested = parse_send(b"\x03MY\x02\x00VAR\x00");
+        assert_eq!(
+            requested,
+            vec![
+                (VarType::UserVar, "MY\x00VAR".to_string()),
+                (VarType::Var, "".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::io;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde_json::json;
+use tokio::sync::broadcast::Sender;
+
+use crate::{
+    net::writable::ObjectWriteStream,
+    transport::{
+        telnet::{
+            processor::TelnetEvent,
+            protocol::{NegotiationType, TelnetOption},
+        },
+        EventData, TransportEvent, TransportEventValue,
+    },
+};
+
+use super::{negotiator::OptionsNegotiatorBuilder, DynWriteStream, TelnetOptionHandler};
+
+const NAMESPACE: &str = "GMCP";
+const RESET_EVENT: &str = "RESET";
+
+const SUPPORTED_PACKAGES: &[&str] = &[
+    "Char 1",
+    "Char.Skills 1",
+    "Char.Items 1",
+    "Comm.Channel 1",
+    "Room 1",
+];
+
+#[derive(Debug, PartialEq)]
+struct GmcpMessage {
+    package: String,
+    payload: Option<serde_json::Value>,
+}
+
+impl GmcpMessage {
+    fn new(package: &str, payload: serde_json::Value) -> Self {
+        Self {
+            package: package.to_string(),
+            payload: Some(payload),
+        }
+    }
+
+    fn parse(data: &[u8]) -> io::Result<Self> {
+        let raw = String::from_utf8_lossy(data);
+        let raw = raw.trim();
+        let (package, json) = match raw.split_once(char::is_whitespace) {
+            Some((package, json)) => (package, json.trim()),
+            None => (raw, ""),
+        };
+
+        if package.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Empty GMCP package name",
+            ));
+        }
+
+        let payload = if json.is_empty() {
+            None
+        } else {
+            match serde_json::from_str(json) {
+                Ok(payload) => Some(payload),
+                Err(err) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid GMCP payload for {package}: {err}"),
+                    ));
+                }
+            }
+        };
+
+        Ok(Self {
+            package: package.to_string(),
+            payload,
+        })
+    }
+
+    fn into_bytes(self) -> Bytes {
+        match self.payload {
+            Some(payload) => Bytes::from(format!("{} {}", self.package, payload)),
+            None => Bytes::from(self.package),
+        }
+    }
+}
+
+pub struct GmcpOptionHandler {
+    events: Sender<TransportEvent>,
+}
+
+impl GmcpOptionHandler {
+    pub fn new(sender: Sender<TransportEvent>) -> Self {
+        GmcpOptionHandler { events: sender }
+    }
+
+    fn reset(&self) {
+        // Don't worry if nobody's around to receive
+        self.events
+            .send(TransportEvent::Event(EventData {
+                ns: NAMESPACE.to_string(),
+                name: RESET_EVENT.to_string(),
+                payload: None,
+            }))
+            .ok();
+    }
+
+    async fn send(&self, stream: &mut DynWriteStream<'_>, message: GmcpMessage) -> io::Result<()> {
+        log::trace!(target: "telnet", ">> GMCP {} {:?}", message.package, message.payload);
+        let command = TelnetEvent::Subnegotiate(TelnetOption::GMCP, message.into_bytes());
+        stream.write_object(command).await
+    }
+}
+
+#[async_trait]
+impl TelnetOptionHandler for GmcpOptionHandler {
+    fn option(&self) -> TelnetOption {
+        TelnetOption::GMCP
+    }
+
+    fn event_namespace(&self) -> Option<&'static str> {
+        Some(NAMESPACE)
+    }
+
+    fn register(&self, negotiator: OptionsNegotiatorBuilder) -> OptionsNegotiatorBuilder {
+        negotiator.accept_will(TelnetOption::GMCP)
+    }
+
+    async fn negotiate(
+        &mut self,
+        negotiation: NegotiationType,
+        mut stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        match negotiation {
+            NegotiationType::Wont => {
+                self.reset();
+            }
+
+            NegotiationType::Will => {
+                let hello = GmcpMessage::new(
+                    "Core.Hello",
+                    json!({
+                        "client": "kodachi",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    }),
+                );
+                self.send(&mut stream, hello).await?;
+
+                let supports = GmcpMessage::new("Core.Supports.Set", json!(SUPPORTED_PACKAGES));
+                self.send(&mut stream, supports).await?;
+            }
+
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn subnegotiate(&mut self, data: Bytes, _stream: DynWriteStream<'_>) -> io::Result<()> {
+        let message = match GmcpMessage::parse(&data) {
+            Ok(message) => message,
+            Err(err) => {
+                // Servers occasionally send malformed JSON; that shouldn't
+                // kill the whole connection
+                log::trace!(target: "telnet", "<< GMCP (invalid) {err}");
+                return Ok(());
+            }
+        };
+        log::trace!(target: "telnet", "<< GMCP {} {:?}", message.package, message.payload);
+
+        self.events
+            .send(TransportEvent::Event(EventData {
+                ns: NAMESPACE.to_string(),
+                name: message.package,
+                payload: message.payload.map(TransportEventValue::from),
+            }))
+            .ok();
+
+        Ok(())
+    }
+
+    async fn send_event(
+        &mut self,
+        event: EventData,
+        mut stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        let payload = match event.payload {
+            Some(payload) => Some(serde_json::to_value(payload)?),
+            None => None,
+        };
+        let message = GmcpMessage {
+            package: event.name,
+            payload,
+        };
+        self.send(&mut stream, message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_message_test() {
+        let message = GmcpMessage::parse(br#"Char.Vitals {"hp": 42, "maxhp": 100}"#).unwrap();
+        assert_eq!(message.package, "Char.Vitals");
+        assert_eq!(message.payload, Some(json!({"hp": 42, "maxhp": 100})));
+    }
+
+    #[test]
+    fn parse_message_without_payload_test() {
+        let message = GmcpMessage::parse(b"Core.Goodbye").unwrap();
+        assert_eq!(message.package, "Core.Goodbye");
+        assert_eq!(message.payload, None);
+    }
+
+    #[test]
+    fn parse_invalid_payload_test() {
+        assert!(GmcpMessage::parse(b"Char.Vitals {hp: ").is_err());
+    }
+
+    #[test]
+    fn serialize_message_test() {
+        let message = GmcpMessage::new("Core.Supports.Set", json!(["Char 1", "Room 1"]));
+        assert_eq!(
+            message.into_bytes(),
+            Bytes::from(r#"Core.Supports.Set ["Char 1","Room 1"]"#)
+        );
+    }
+
+    #[test]
+    fn convert_payload_test() {
+        let value: TransportEventValue = json!({"hp": 42, "name": "Adora"}).into();
+        let serialized = serde_json::to_value(value).unwrap();
+        assert_eq!(serialized, json!({"hp": 42, "name": "Adora"}));
+    }
+}
@@ -1,15 +1,49 @@
 use std::{
     io::{self},
     mem,
-    task::Poll,
+    task::{ready, Poll},
 };
 
 use async_compression::tokio::bufread::ZlibDecoder;
 use bytes::{Bytes, BytesMut};
+use flate2::{Compress, Compression, FlushCompress, Status};
 use log::trace;
 use pin_project::pin_project;
 use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, BufReader, ReadBuf};
 
+/// Compress all of `input` into `output`, using the given `flush` mode
+fn deflate(
+    compressor: &mut Compress,
+    mut input: &[u8],
+    flush: FlushCompress,
+    output: &mut Vec<u8>,
+) -> io::Result<()> {
+    loop {
+        output.reserve(input.len() + 64);
+
+        let before_in = compressor.total_in();
+        let status = compressor.compress_vec(input, output, flush)?;
+        let consumed = (compressor.total_in() - before_in) as usize;
+        input = &input[consumed..];
+
+        // NOTE: If the compressor didn't fill up the output buffer, it's done flushing
+        let has_room = output.len() < output.capacity();
+        match status {
+            Status::StreamEnd => return Ok(()),
+            _ if input.is_empty() && has_room && flush != FlushCompress::Finish => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn finish_compressing(compressor: &mut Option<Compress>, output: &mut Vec<u8>) -> io::Result<()> {
+    if let Some(mut compressor) = compressor.take() {
+        deflate(&mut compressor, &[], FlushCompress::Finish, output)?;
+        trace!(target: "mccp", "Disabled compression!");
+    }
+    Ok(())
+}
+
 #[pin_project]
 struct PrefixedStream<S: AsyncBufRead> {
     prefix: Bytes,
@@ -156,13 +190,38 @@ impl<S: AsyncRead + AsyncWrite> AsyncWrite for State<S> {
 pub struct CompressableStream<S: AsyncRead> {
     #[pin]
     stream: State<S>,
+
+    compressor: Option<Compress>,
+
+    // Compressed bytes waiting to be written to `stream`
+    pending_write: Vec<u8>,
 }
 
 impl<S: AsyncRead> CompressableStream<S> {
     pub fn new(stream: S) -> Self {
         CompressableStream {
             stream: State::Uncompressed(stream),
+            compressor: None,
+            pending_write: Vec::default(),
+        }
+    }
+
+    pub fn is_compressing(&self) -> bool {
+        self.compressor.is_some()
+    }
+
+    pub fn start_compressing(&mut self) {
+        if self.compressor.is_some() {
+            panic!("start_compressing() while already started");
         }
+        self.compressor = Some(Compress::new(Compression::default(), true));
+        trace!(target: "mccp", "Enabled compression!");
+    }
+
+    /// Finish the compressed stream. Any further writes will be uncompressed. The
+    /// end of the compressed stream will be written on the next write or flush.
+    pub fn stop_compressing(&mut self) -> io::Result<()> {
+        finish_compressing(&mut self.compressor, &mut self.pending_write)
     }
 
     pub fn start_decompressing(&mut self, pending: Option<&mut BytesMut>) {
@@ -239,26 +298,63 @@ impl<S: AsyncRead + Unpin> AsyncRead for CompressableStream<S> {
     }
 }
 
+impl<S: AsyncWrite + AsyncRead> CompressableStream<S> {
+    fn poll_write_pending(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let mut this = self.project();
+        while !this.pending_write.is_empty() {
+            let written = ready!(this.stream.as_mut().poll_write(cx, this.pending_write))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            this.pending_write.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
 impl<S: AsyncWrite + AsyncRead> AsyncWrite for CompressableStream<S> {
     fn poll_write(
-        self: std::pin::Pin<&mut Self>,
+        mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize, std::io::Error>> {
-        self.project().stream.poll_write(cx, buf)
+        ready!(self.as_mut().poll_write_pending(cx))?;
+
+        let this = self.as_mut().project();
+        let Some(compressor) = this.compressor else {
+            return this.stream.poll_write(cx, buf);
+        };
+
+        deflate(compressor, buf, FlushCompress::Sync, this.pending_write)?;
+
+        // We've accepted `buf`, so it's okay if this is Pending; whatever's left
+        // will get written out on the next write or flush
+        if let Poll::Ready(Err(err)) = self.poll_write_pending(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(buf.len()))
     }
 
     fn poll_flush(
-        self: std::pin::Pin<&mut Self>,
+        mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Result<(), std::io::Error>> {
+        ready!(self.as_mut().poll_write_pending(cx))?;
         self.project().stream.poll_flush(cx)
     }
 
     fn poll_shutdown(
-        self: std::pin::Pin<&mut Self>,
+        mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Result<(), std::io::Error>> {
+        // Cleanly terminate the compressed stream before shutting down
+        let this = self.as_mut().project();
+        finish_compressing(this.compressor, this.pending_write)?;
+        ready!(self.as_mut().poll_write_pending(cx))?;
         self.project().stream.poll_shutdown(cx)
     }
 }
@@ -269,8 +365,8 @@ mod tests {
 
     use async_compression::tokio::bufread::ZlibEncoder;
     use bytes::{BufMut, Bytes, BytesMut};
-    use flate2::{Compress, Compression, Status};
-    use tokio::io::AsyncReadExt;
+    use flate2::{Decompress, FlushDecompress};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
     use super::*;
 
@@ -332,6 +428,60 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn compress_writes_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut compressable = CompressableStream::new(local);
+
+        compressable.write_all(b"IAC SB MCCP3").await?;
+        compressable.start_compressing();
+        compressable.write_all(b"For the honor").await?;
+        compressable.write_all(b" of Grayskull!").await?;
+        compressable.shutdown().await?;
+
+        let mut received = Vec::default();
+        remote.read_to_end(&mut received).await?;
+        assert!(received.starts_with(b"IAC SB MCCP3"));
+
+        let mut decompressed = String::default();
+        ZlibDecoder::new(&received[12..])
+            .read_to_string(&mut decompressed)
+            .await?;
+        assert_eq!(decompressed, "For the honor of Grayskull!");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stop_compressing_test() -> io::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut compressable = CompressableStream::new(local);
+
+        compressable.start_compressing();
+        compressable.write_all(b"For the honor").await?;
+        compressable.stop_compressing()?;
+        assert!(!compressable.is_compressing());
+
+        compressable.write_all(b" of Grayskull!").await?;
+        compressable.shutdown().await?;
+
+        let mut received = Vec::default();
+        remote.read_to_end(&mut received).await?;
+
+        let mut decoder = Decompress::new(true);
+        let mut decompressed = Vec::with_capacity(256);
+        let status = decoder
+            .decompress_vec(&received, &mut decompressed, FlushDecompress::Finish)
+            .expect("Failed to decompress");
+        assert_eq!(status, Status::StreamEnd);
+        assert_eq!(decompressed, b"For the honor");
+
+        let trailer = &received[decoder.total_in() as usize..];
+        assert_eq!(trailer, b" of Grayskull!");
+
+        Ok(())
+    }
+
     async fn test_decompress_round_trip(input: &str) -> io::Result<()> {
         let mut compressor = Compress::new(Compression::default(), true);
 
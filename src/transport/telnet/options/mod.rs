@@ -7,10 +7,16 @@ use tokio::{
     sync::broadcast::{self, Receiver},
 };
 
-use crate::transport::{EventData, TransportNotification};
+use crate::transport::{EventData, TransportEvent, TransportNotification};
 
 use self::{
+    charset::CharsetOptionHandler,
+    echo::EchoOptionHandler,
+    environ::NewEnvironOptionHandler,
+    gmcp::GmcpOptionHandler,
     msdp::MsdpOptionHandler,
+    mssp::MsspOptionHandler,
+    mxp::MxpOptionHandler,
     naws::NawsOptionHandler,
     negotiator::{OptionsNegotiator, OptionsNegotiatorBuilder},
     ttype::TermTypeOptionHandler,
@@ -18,12 +24,22 @@ use self::{
 
 use super::protocol::{NegotiationType, TelnetOption};
 
+pub mod charset;
+pub mod echo;
+pub mod environ;
+pub mod gmcp;
 pub mod mccp;
 pub mod msdp;
+pub mod mssp;
+pub mod mxp;
 pub mod naws;
 pub mod negotiator;
 pub mod ttype;
 
+// NOTE: Events are drained right after each (sub)negotiation, so handlers only
+// need room for the few events any single one of those might produce.
+const EVENTS_CAPACITY: usize = 64;
+
 // NOTE: We need to Box the Stream type in order for TelnetOptionHandler to be object-safe.
 pub type DynWriteStream<'a> = Box<&'a mut (dyn AsyncWrite + Unpin + Send)>;
 
@@ -36,6 +52,12 @@ pub trait TelnetOptionHandler: Send {
     }
 
     fn option(&self) -> TelnetOption;
+
+    /// The `ns` of events this handler can send via [send_event], if any
+    fn event_namespace(&self) -> Option<&'static str> {
+        None
+    }
+
     fn register(&self, negotiator: OptionsNegotiatorBuilder) -> OptionsNegotiatorBuilder;
 
     async fn negotiate(
@@ -57,12 +79,23 @@ pub trait TelnetOptionHandler: Send {
     async fn subnegotiate(&mut self, _data: Bytes, _stream: DynWriteStream<'_>) -> io::Result<()> {
         Ok(())
     }
+
+    async fn send_event(
+        &mut self,
+        event: EventData,
+        _stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unable to send {} events", event.ns()),
+        ))
+    }
 }
 
 pub struct TelnetOptionsManager {
     negotiator: OptionsNegotiator,
     handlers: HashMap<TelnetOption, Box<dyn TelnetOptionHandler>>,
-    events: Receiver<EventData>,
+    events: Receiver<TransportEvent>,
 }
 
 impl Default for TelnetOptionsManager {
@@ -70,14 +103,25 @@ impl Default for TelnetOptionsManager {
         let mut negotiator_builder = OptionsNegotiatorBuilder::default();
         let mut handlers: HashMap<TelnetOption, Box<dyn TelnetOptionHandler>> = Default::default();
 
-        let (events_sender, events) = broadcast::channel(1);
-        let msdp = MsdpOptionHandler::new(events_sender);
+        let (events_sender, events) = broadcast::channel(EVENTS_CAPACITY);
+        let charset = CharsetOptionHandler::new(events_sender.clone());
+        let echo = EchoOptionHandler::new(events_sender.clone());
+        let gmcp = GmcpOptionHandler::new(events_sender.clone());
+        let msdp = MsdpOptionHandler::new(events_sender.clone());
+        let mssp = MsspOptionHandler::new(events_sender.clone());
+        let mxp = MxpOptionHandler::new(events_sender);
 
         // All handlers:
         let all_handlers: Vec<Box<dyn TelnetOptionHandler>> = vec![
             Box::new(NawsOptionHandler::default()),
             Box::new(TermTypeOptionHandler::default()),
+            Box::new(NewEnvironOptionHandler::default()),
+            Box::new(charset),
+            Box::new(echo),
+            Box::new(gmcp),
             Box::new(msdp),
+            Box::new(mssp),
+            Box::new(mxp),
         ];
 
         // Register with the builder
@@ -90,6 +134,9 @@ impl Default for TelnetOptionsManager {
         let negotiator = negotiator_builder
             .accept_will(TelnetOption::EOR)
             .accept_will(TelnetOption::MCCP2)
+            .accept_will(TelnetOption::MCCP3)
+            .accept_will(TelnetOption::MSP)
+            .accept_will(TelnetOption::SuppressGoAhead)
             .build();
 
         TelnetOptionsManager {
@@ -108,8 +155,19 @@ impl TelnetOptionsManager {
         self.negotiator.on_connected(stream).await
     }
 
-    pub async fn recv_event(&mut self) -> Option<EventData> {
-        self.events.recv().await.ok()
+    pub fn is_accepted(&self, option: TelnetOption) -> bool {
+        self.negotiator.is_accepted(option)
+    }
+
+    /// Collect any events emitted by handlers. Handlers only emit events while
+    /// (sub)negotiating, so these are returned from those methods directly,
+    /// allowing the transport to yield them in order with the received data.
+    fn drain_events(&mut self) -> Vec<TransportEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            events.push(event);
+        }
+        events
     }
 
     pub async fn notify<S: AsyncWrite + Unpin + Send>(
@@ -128,7 +186,7 @@ impl TelnetOptionsManager {
         negotiation: NegotiationType,
         option: TelnetOption,
         stream: &mut S,
-    ) -> io::Result<()> {
+    ) -> io::Result<Vec<TransportEvent>> {
         if let Some(handler) = self.handlers.get_mut(&option) {
             if !handler.will_answer_negotiation() {
                 self.negotiator
@@ -139,10 +197,40 @@ impl TelnetOptionsManager {
             let wrapped: Box<&mut (dyn AsyncWrite + Unpin + Send)> = Box::new(stream);
 
             handler.negotiate(negotiation, wrapped).await?;
-            Ok(())
         } else {
-            self.negotiator.negotiate(negotiation, option, stream).await
+            self.negotiator
+                .negotiate(negotiation, option, stream)
+                .await?;
         }
+
+        Ok(self.drain_events())
+    }
+
+    pub async fn send_event<S: AsyncWrite + Unpin + Send>(
+        &mut self,
+        event: EventData,
+        stream: &mut S,
+    ) -> io::Result<()> {
+        let Some(handler) = self
+            .handlers
+            .values_mut()
+            .find(|handler| handler.event_namespace() == Some(event.ns()))
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unsupported event namespace: {}", event.ns()),
+            ));
+        };
+
+        if !self.negotiator.is_accepted(handler.option()) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} has not been negotiated", event.ns()),
+            ));
+        }
+
+        let wrapped: Box<&mut (dyn AsyncWrite + Unpin + Send)> = Box::new(stream);
+        handler.send_event(event, wrapped).await
     }
 
     pub async fn subnegotiate<S: AsyncWrite + Unpin + Send>(
@@ -150,13 +238,13 @@ impl TelnetOptionsManager {
         option: TelnetOption,
         data: Bytes,
         stream: &mut S,
-    ) -> io::Result<()> {
+    ) -> io::Result<Vec<TransportEvent>> {
         if self.negotiator.is_accepted(option) {
             if let Some(handler) = self.handlers.get_mut(&option) {
                 let wrapped: Box<&mut (dyn AsyncWrite + Unpin + Send)> = Box::new(stream);
                 handler.subnegotiate(data, wrapped).await?;
             }
         }
-        Ok(())
+        Ok(self.drain_events())
     }
 }
@@ -14,7 +14,7 @@ use crate::{
             processor::TelnetEvent,
             protocol::{NegotiationType, TelnetOption},
         },
-        EventData, TransportEventValue,
+        EventData, TransportEvent, TransportEventValue,
     },
 };
 
@@ -23,7 +23,7 @@ use super::{negotiator::OptionsNegotiatorBuilder, DynWriteStream, TelnetOptionHa
 const NAMESPACE: &str = "MSDP";
 const RESET_EVENT: &str = "RESET";
 
-const MSDP_VAR: u8 = 1;
+pub(super) const MSDP_VAR: u8 = 1;
 const MSDP_VAL: u8 = 2;
 const MSDP_TABLE_OPEN: u8 = 3;
 const MSDP_TABLE_CLOSE: u8 = 4;
@@ -33,6 +33,19 @@ const MSDP_ARRAY_CLOSE: u8 = 6;
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct MsdpVar(MsdpName, MsdpVal);
 
+impl MsdpVar {
+    fn from_event(event: EventData) -> Self {
+        let name = MsdpName::from_string(event.name);
+        let value = event
+            .payload
+            .unwrap_or_else(|| TransportEventValue::String("".to_string()));
+        let options = MsdpValOptions {
+            flat_array: name.is_command() && matches!(value, TransportEventValue::Vec(_)),
+        };
+        MsdpVar(name, MsdpVal(value, options))
+    }
+}
+
 impl Writable for MsdpVar {
     fn write<S: io::Write>(self, stream: &mut S) -> io::Result<()> {
         self.0.write(stream)?;
@@ -71,6 +84,8 @@ pub enum MsdpName {
     Commands,
     List,
     Report,
+    Reset,
+    Send,
     Unreport,
     Other(String),
 }
@@ -81,11 +96,25 @@ impl MsdpName {
             "COMMANDS" => MsdpName::Commands,
             "LIST" => MsdpName::List,
             "REPORT" => MsdpName::Report,
+            "RESET" => MsdpName::Reset,
+            "SEND" => MsdpName::Send,
             "UNREPORT" => MsdpName::Unreport,
             _ => MsdpName::Other(name),
         }
     }
 
+    /// Commands accept multiple values as a "flat" array of MSDP_VALs
+    fn is_command(&self) -> bool {
+        matches!(
+            self,
+            MsdpName::List
+                | MsdpName::Report
+                | MsdpName::Reset
+                | MsdpName::Send
+                | MsdpName::Unreport
+        )
+    }
+
     fn into_string(self) -> String {
         match self {
             MsdpName::Other(s) => s,
@@ -137,6 +166,9 @@ impl Writable for MsdpVal {
 
         match (self.0, self.1.flat_array) {
             (TransportEventValue::String(s), _) => stream.write_all(&s.as_bytes()),
+            (TransportEventValue::Bool(b), _) => stream.write_all(b.to_string().as_bytes()),
+            (TransportEventValue::Number(n), _) => stream.write_all(n.to_string().as_bytes()),
+            (TransportEventValue::Null, _) => Ok(()),
             (TransportEventValue::Vec(items), false) => {
                 stream.write_all(&[MSDP_ARRAY_OPEN])?;
                 for item in items {
@@ -163,7 +195,7 @@ impl Writable for MsdpVal {
 }
 
 #[derive(Debug, PartialEq, Eq)]
-enum ReadableMsdpVal {
+pub(super) enum ReadableMsdpVal {
     None,
     Ok(TransportEventValue),
     ArrayClose,
@@ -246,11 +278,11 @@ impl Readable for ReadableMsdpVal {
 }
 
 pub struct MsdpOptionHandler {
-    events: Sender<EventData>,
+    events: Sender<TransportEvent>,
 }
 
 impl MsdpOptionHandler {
-    pub fn new(sender: Sender<EventData>) -> Self {
+    pub fn new(sender: Sender<TransportEvent>) -> Self {
         MsdpOptionHandler { events: sender }
     }
 }
@@ -259,11 +291,11 @@ impl MsdpOptionHandler {
     fn reset(&self) {
         // Don't worry if nobody's around to receive
         self.events
-            .send(EventData {
+            .send(TransportEvent::Event(EventData {
                 ns: NAMESPACE.to_string(),
                 name: RESET_EVENT.to_string(),
                 payload: None,
-            })
+            }))
             .ok();
     }
 }
@@ -274,6 +306,10 @@ impl TelnetOptionHandler for MsdpOptionHandler {
         TelnetOption::MSDP
     }
 
+    fn event_namespace(&self) -> Option<&'static str> {
+        Some(NAMESPACE)
+    }
+
     fn register(&self, negotiator: OptionsNegotiatorBuilder) -> OptionsNegotiatorBuilder {
         negotiator.accept_will(TelnetOption::MSDP)
     }
@@ -317,15 +353,27 @@ impl TelnetOptionHandler for MsdpOptionHandler {
         }
 
         self.events
-            .send(EventData {
+            .send(TransportEvent::Event(EventData {
                 ns: NAMESPACE.to_string(),
                 name: var.0.into_string(),
                 payload: Some((var.1).0),
-            })
+            }))
             .ok();
 
         Ok(())
     }
+
+    async fn send_event(
+        &mut self,
+        event: EventData,
+        mut stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        let to_send = MsdpVar::from_event(event);
+        log::trace!(target: "telnet", ">> MSDP {:?} {:?}", to_send.0, to_send.1);
+
+        let command = TelnetEvent::Subnegotiate(TelnetOption::MSDP, to_send.into_bytes());
+        stream.write_object(command).await
+    }
 }
 
 #[cfg(test)]
@@ -341,6 +389,48 @@ mod tests {
         assert_eq!(bytes, Bytes::from("\x01LIST\x02COMMANDS"));
     }
 
+    #[test]
+    fn serialize_report_event_test() {
+        let event = EventData {
+            ns: NAMESPACE.to_string(),
+            name: "REPORT".to_string(),
+            payload: Some(TransportEventValue::Vec(vec![
+                TransportEventValue::String("HEALTH".to_string()),
+                TransportEventValue::String("MANA".to_string()),
+            ])),
+        };
+        let bytes = MsdpVar::from_event(event).into_bytes();
+        assert_eq!(bytes, Bytes::from("\x01REPORT\x02HEALTH\x02MANA"));
+    }
+
+    #[test]
+    fn serialize_single_report_event_test() {
+        let event = EventData {
+            ns: NAMESPACE.to_string(),
+            name: "REPORT".to_string(),
+            payload: Some(TransportEventValue::String("HEALTH".to_string())),
+        };
+        let bytes = MsdpVar::from_event(event).into_bytes();
+        assert_eq!(bytes, Bytes::from("\x01REPORT\x02HEALTH"));
+    }
+
+    #[test]
+    fn serialize_variable_event_test() {
+        let event = EventData {
+            ns: NAMESPACE.to_string(),
+            name: "TARGETS".to_string(),
+            payload: Some(TransportEventValue::Vec(vec![
+                TransportEventValue::String("Hordak".to_string()),
+                TransportEventValue::String("Catra".to_string()),
+            ])),
+        };
+        let bytes = MsdpVar::from_event(event).into_bytes();
+        assert_eq!(
+            bytes,
+            Bytes::from("\x01TARGETS\x02\x05\x02Hordak\x02Catra\x06")
+        );
+    }
+
     #[test]
     fn read_simple_array_value_test() {
         let original = MsdpVal::array(vec![
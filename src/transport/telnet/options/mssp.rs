@@ -0,0 +1,136 @@
+use std::{collections::HashMap, io};
+
+use async_trait::async_trait;
+use bytes::{Buf, Bytes};
+use tokio::sync::broadcast::Sender;
+
+use crate::{
+    net::readable::Readable,
+    transport::{telnet::protocol::TelnetOption, EventData, TransportEvent, TransportEventValue},
+};
+
+use super::{
+    msdp::{ReadableMsdpVal, MSDP_VAR},
+    negotiator::OptionsNegotiatorBuilder,
+    DynWriteStream, TelnetOptionHandler,
+};
+
+pub const NAMESPACE: &str = "MSSP";
+pub const STATUS_EVENT: &str = "STATUS";
+
+/// MSSP uses the same VAR/VAL encoding as MSDP (without tables or arrays); a variable
+/// with multiple values is represented as an array of strings.
+fn parse_mssp(data: Bytes) -> io::Result<HashMap<String, TransportEventValue>> {
+    let mut stream = data.reader();
+    let mut status = HashMap::new();
+
+    loop {
+        let name = match ReadableMsdpVal::read(&mut stream)? {
+            ReadableMsdpVal::Ok(TransportEventValue::String(name)) => name,
+            ReadableMsdpVal::None => break,
+            unexpected => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Expected MSSP_VAR but got {:?}", unexpected),
+                ));
+            }
+        };
+
+        let mut values = Vec::new();
+        while !matches!(
+            io::BufRead::fill_buf(&mut stream)?.first(),
+            Some(&MSDP_VAR) | None
+        ) {
+            match ReadableMsdpVal::read(&mut stream)? {
+                ReadableMsdpVal::Ok(value) => values.push(value),
+                _ => break,
+            }
+        }
+
+        let value = if values.len() == 1 {
+            values.remove(0)
+        } else {
+            TransportEventValue::Vec(values)
+        };
+        status.insert(name, value);
+    }
+
+    Ok(status)
+}
+
+pub struct MsspOptionHandler {
+    events: Sender<TransportEvent>,
+}
+
+impl MsspOptionHandler {
+    pub fn new(sender: Sender<TransportEvent>) -> Self {
+        MsspOptionHandler { events: sender }
+    }
+}
+
+#[async_trait]
+impl TelnetOptionHandler for MsspOptionHandler {
+    fn option(&self) -> TelnetOption {
+        TelnetOption::MSSP
+    }
+
+    fn register(&self, negotiator: OptionsNegotiatorBuilder) -> OptionsNegotiatorBuilder {
+        negotiator.accept_will(TelnetOption::MSSP)
+    }
+
+    async fn subnegotiate(&mut self, data: Bytes, _stream: DynWriteStream<'_>) -> io::Result<()> {
+        let status = parse_mssp(data)?;
+        log::trace!(target: "telnet", "<< MSSP {:?}", status);
+
+        self.events
+            .send(TransportEvent::Event(EventData {
+                ns: NAMESPACE.to_string(),
+                name: STATUS_EVENT.to_string(),
+                payload: Some(TransportEventValue::Map(status)),
+            }))
+            .ok();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_status_test() {
+        let status = parse_mssp(Bytes::from(
+            "\x01NAME\x02Eternia\x01PLAYERS\x0242\x01PORT\x024000\x024001",
+        ))
+        .unwrap();
+        assert_eq!(
+            status["NAME"],
+            TransportEventValue::String("Eternia".to_string())
+        );
+        assert_eq!(
+            status["PLAYERS"],
+            TransportEventValue::String("42".to_string())
+        );
+        assert_eq!(
+            status["PORT"],
+            TransportEventValue::Vec(vec![
+                TransportEventValue::String("4000".to_string()),
+                TransportEventValue::String("4001".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_empty_value_test() {
+        let status = parse_mssp(Bytes::from("\x01CODEBASE\x02\x01NAME\x02Eternia")).unwrap();
+        assert_eq!(
+            status["CODEBASE"],
+            TransportEventValue::String("".to_string())
+        );
+        assert_eq!(
+            status["NAME"],
+            TransportEventValue::String("Eternia".to_string())
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use std::io;
+
+use async_trait::async_trait;
+use tokio::sync::broadcast::Sender;
+
+use crate::transport::{
+    telnet::protocol::{NegotiationType, TelnetOption},
+    TransportEvent,
+};
+
+use super::{negotiator::OptionsNegotiatorBuilder, DynWriteStream, TelnetOptionHandler};
+
+/// Tracks whether the server has enabled MXP. The tags themselves are inline with
+/// the text, so parsing them is left to the TextProcessor.
+pub struct MxpOptionHandler {
+    events: Sender<TransportEvent>,
+}
+
+impl MxpOptionHandler {
+    pub fn new(sender: Sender<TransportEvent>) -> Self {
+        MxpOptionHandler { events: sender }
+    }
+}
+
+#[async_trait]
+impl TelnetOptionHandler for MxpOptionHandler {
+    fn option(&self) -> TelnetOption {
+        TelnetOption::MXP
+    }
+
+    fn register(&self, negotiator: OptionsNegotiatorBuilder) -> OptionsNegotiatorBuilder {
+        negotiator.accept_will(TelnetOption::MXP)
+    }
+
+    async fn negotiate(
+        &mut self,
+        negotiation: NegotiationType,
+        _stream: DynWriteStream<'_>,
+    ) -> io::Result<()> {
+        let enabled = match negotiation {
+            NegotiationType::Will => true,
+            NegotiationType::Wont => false,
+            _ => return Ok(()),
+        };
+
+        log::trace!(target: "telnet", "mxp: {enabled}");
+        self.events.send(TransportEvent::Mxp { enabled }).ok();
+        Ok(())
+    }
+}
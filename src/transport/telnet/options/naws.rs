@@ -92,6 +92,8 @@ impl TelnetOptionHandler for NawsOptionHandler {
             TransportNotification::WindowSize { width, height } => {
                 self.set_size(*width, *height, &mut stream).await?;
             }
+
+            _ => {}
         }
 
         Ok(())
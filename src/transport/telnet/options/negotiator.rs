@@ -10,24 +10,42 @@ use crate::transport::telnet::{
     protocol::{NegotiationType, TelnetOption},
 };
 
+/// Which side of the connection an option is enabled for. Options may be
+/// negotiated independently for each side.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+enum Side {
+    /// We perform the option (the server sent DO, and we replied WILL)
+    Local,
+
+    /// The server performs the option (it sent WILL, and we replied DO)
+    Remote,
+}
+
+impl Side {
+    fn of(negotiation: NegotiationType) -> Self {
+        match negotiation {
+            NegotiationType::Do | NegotiationType::Dont => Side::Local,
+            NegotiationType::Will | NegotiationType::Wont => Side::Remote,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum OptionState {
-    Accept(NegotiationType),
-    Will,
-    Do,
+    Accept,
+    Enabled,
 }
 
 pub struct OptionsNegotiator {
-    options: HashMap<TelnetOption, OptionState>,
+    options: HashMap<(TelnetOption, Side), OptionState>,
     will: HashSet<TelnetOption>,
 }
 
 impl OptionsNegotiator {
     pub fn is_accepted(&self, option: TelnetOption) -> bool {
-        matches!(
-            self.options.get(&option),
-            Some(OptionState::Will | OptionState::Do)
-        )
+        [Side::Local, Side::Remote]
+            .iter()
+            .any(|side| self.options.get(&(option, *side)) == Some(&OptionState::Enabled))
     }
 
     pub async fn on_connected<S: AsyncWrite + Unpin + Send>(
@@ -48,47 +66,36 @@ impl OptionsNegotiator {
         option: TelnetOption,
         stream: &mut S,
     ) -> io::Result<()> {
-        if let Some(state) = self.options.get(&option) {
-            match (negotiation, state) {
-                (_, &OptionState::Accept(negotiation)) => {
-                    let (state, response_type) = match negotiation {
-                        NegotiationType::Do => (OptionState::Do, NegotiationType::Will),
-                        NegotiationType::Will => (OptionState::Will, NegotiationType::Do),
-                        _ => panic!("Impossible negotiation {:?} for {:?}", negotiation, option),
-                    };
-                    self.options.insert(option, state);
-
-                    TelnetEvent::Negotiate(response_type, option)
-                        .write_all(stream)
-                        .await?;
-
-                    return Ok(());
-                }
+        let key = (option, Side::of(negotiation));
+        let state = self.options.get(&key).copied();
 
-                (NegotiationType::Do, &OptionState::Do)
-                | (NegotiationType::Will, &OptionState::Will) => {
-                    // Already accepted; this is a nop
-                    return Ok(());
-                }
+        let response_type = match (negotiation, state) {
+            (NegotiationType::Do | NegotiationType::Will, Some(OptionState::Enabled)) => {
+                // Already accepted; this is a nop
+                None
+            }
 
-                _ => {} // Ignore and fall through below:
+            (NegotiationType::Do, Some(OptionState::Accept)) => {
+                self.options.insert(key, OptionState::Enabled);
+                Some(NegotiationType::Will)
+            }
+            (NegotiationType::Will, Some(OptionState::Accept)) => {
+                self.options.insert(key, OptionState::Enabled);
+                Some(NegotiationType::Do)
             }
-        }
 
-        let response_type = match negotiation {
-            NegotiationType::Do => Some(NegotiationType::Wont),
-            NegotiationType::Will => Some(NegotiationType::Dont),
-            NegotiationType::Dont => {
-                if self.options.get(&option) == Some(&OptionState::Do) {
-                    self.options
-                        .insert(option, OptionState::Accept(NegotiationType::Do));
+            (NegotiationType::Do, None) => Some(NegotiationType::Wont),
+            (NegotiationType::Will, None) => Some(NegotiationType::Dont),
+
+            (NegotiationType::Dont, state) => {
+                if state == Some(OptionState::Enabled) {
+                    self.options.insert(key, OptionState::Accept);
                 }
                 Some(NegotiationType::Wont)
             }
-            NegotiationType::Wont => {
-                if self.options.get(&option) == Some(&OptionState::Will) {
-                    self.options
-                        .insert(option, OptionState::Accept(NegotiationType::Will));
+            (NegotiationType::Wont, state) => {
+                if state == Some(OptionState::Enabled) {
+                    self.options.insert(key, OptionState::Accept);
                 }
                 None
             }
@@ -106,7 +113,7 @@ impl OptionsNegotiator {
 
 #[derive(Default)]
 pub struct OptionsNegotiatorBuilder {
-    options: HashMap<TelnetOption, OptionState>,
+    options: HashMap<(TelnetOption, Side), OptionState>,
     will: HashSet<TelnetOption>,
 }
 
@@ -120,13 +127,13 @@ impl OptionsNegotiatorBuilder {
 
     pub fn accept_do(mut self, option: TelnetOption) -> Self {
         self.options
-            .insert(option, OptionState::Accept(NegotiationType::Do));
+            .insert((option, Side::Local), OptionState::Accept);
         self
     }
 
     pub fn accept_will(mut self, option: TelnetOption) -> Self {
         self.options
-            .insert(option, OptionState::Accept(NegotiationType::Will));
+            .insert((option, Side::Remote), OptionState::Accept);
         self
     }
 
@@ -246,4 +253,34 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn accept_both_sides_test() -> io::Result<()> {
+        let mut handler = OptionsNegotiatorBuilder::default()
+            .accept_do(TelnetOption::Charset)
+            .accept_will(TelnetOption::Charset)
+            .build();
+
+        let mut stream = TestStream::new();
+
+        handler
+            .negotiate(NegotiationType::Will, TelnetOption::Charset, &mut stream)
+            .await?;
+        assert!(handler.is_accepted(TelnetOption::Charset));
+
+        handler
+            .negotiate(NegotiationType::Do, TelnetOption::Charset, &mut stream)
+            .await?;
+
+        let mut expected_stream = TestStream::new();
+        TelnetEvent::Negotiate(NegotiationType::Do, TelnetOption::Charset)
+            .write_all(&mut expected_stream)
+            .await?;
+        TelnetEvent::Negotiate(NegotiationType::Will, TelnetOption::Charset)
+            .write_all(&mut expected_stream)
+            .await?;
+        assert_eq!(stream.sent, expected_stream.sent);
+
+        Ok(())
+    }
 }
@@ -18,6 +18,32 @@ const MTTS_UTF8: u16 = 4;
 const MTTS_256COLOR: u16 = 8;
 const MTTS_TRUECOLOR: u16 = 256;
 
+/// Compute the MTTS capabilities bit vector for the current terminal
+pub(super) fn mtts_bit_vector() -> u16 {
+    let mut bit_vector = 0;
+
+    if let Some(colors) = supports_color::on_cached(supports_color::Stream::Stdout) {
+        if colors.has_basic {
+            bit_vector += MTTS_ANSI;
+        }
+        if colors.has_256 {
+            bit_vector += MTTS_256COLOR;
+        }
+        if colors.has_16m {
+            bit_vector += MTTS_TRUECOLOR;
+        }
+    }
+
+    if supports_unicode::on(supports_unicode::Stream::Stdout) {
+        bit_vector += MTTS_UTF8;
+    }
+
+    // Just assume VT100 for now I guess
+    bit_vector += MTTS_VT100;
+
+    bit_vector
+}
+
 enum State {
     ClientName,
     TermType,
@@ -90,30 +116,7 @@ impl TermTypeOptionHandler {
         match self.state {
             State::ClientName => buf.put_slice(b"kodachi"),
             State::TermType => buf.put_slice(env::var("TERM").unwrap_or("".to_string()).as_bytes()),
-            State::MttsBitVector => {
-                let mut bit_vector = 0;
-
-                if let Some(colors) = supports_color::on_cached(supports_color::Stream::Stdout) {
-                    if colors.has_basic {
-                        bit_vector += MTTS_ANSI;
-                    }
-                    if colors.has_256 {
-                        bit_vector += MTTS_256COLOR;
-                    }
-                    if colors.has_16m {
-                        bit_vector += MTTS_TRUECOLOR;
-                    }
-                }
-
-                if supports_unicode::on(supports_unicode::Stream::Stdout) {
-                    bit_vector += MTTS_UTF8;
-                }
-
-                // Just assume VT100 for now I guess
-                bit_vector += MTTS_VT100;
-
-                buf.put_slice(format!("MTTS {}", bit_vector).as_bytes())
-            }
+            State::MttsBitVector => buf.put_slice(format!("MTTS {}", mtts_bit_vector()).as_bytes()),
         }
     }
 
@@ -72,6 +72,7 @@ macro_rules! declare_type {
 
 declare_type!(TelnetCommand {
     EOR => 239,
+    Nop => 241,
     GoAhead => 249,
 });
 
@@ -82,10 +83,13 @@ declare_type!(TelnetOption {
     EOR => 25,
     // Negotiate About Window Size
     Naws => 31,
+    NewEnviron => 39,
     Charset => 42,
     MSDP => 69,
+    MSSP => 70,
     MCCP2 => 86,
     MCCP3 => 87,
     MSP => 90,
+    MXP => 91,
     GMCP => 201,
 });
@@ -0,0 +1,197 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures::{ready, SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+    time,
+};
+use tokio_tungstenite::{
+    client_async,
+    tungstenite::{client::IntoClientRequest, http::HeaderValue, Error as WsError, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::net::{websocket::WebSocketOptions, Uri};
+
+use super::connect::{connect_tcp, connect_tls, ConnectOptions};
+
+/// Adapts a WebSocket into a plain byte stream. Each received Text or Binary
+/// frame is read as-is, and everything written between flushes is sent as a
+/// single frame.
+pub struct WebSocketIo<S> {
+    ws: WebSocketStream<S>,
+    incoming: Bytes,
+    outgoing: BytesMut,
+
+    /// If set, all frames are sent as Binary; otherwise, frames that are valid
+    /// UTF-8 are sent as Text
+    binary: bool,
+}
+
+impl WebSocketIo<MaybeTlsStream<TcpStream>> {
+    pub async fn connect(
+        uri: &Uri,
+        websocket: &WebSocketOptions,
+        options: &mut ConnectOptions<'_>,
+    ) -> io::Result<Self> {
+        let mut request = websocket
+            .url(&uri.host, uri.port, uri.tls)
+            .into_client_request()
+            .map_err(into_io_error)?;
+        if let Some(protocol) = &websocket.protocol {
+            request.headers_mut().insert(
+                "Sec-WebSocket-Protocol",
+                HeaderValue::from_str(protocol)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+            );
+        }
+
+        let timeout = options.timeout;
+        let connect = async {
+            let stream = if uri.tls {
+                MaybeTlsStream::NativeTls(
+                    connect_tls(&uri.host, uri.port, &uri.tls_options, options).await?,
+                )
+            } else {
+                MaybeTlsStream::Plain(connect_tcp(&uri.host, uri.port, options).await?)
+            };
+
+            client_async(request, stream)
+                .await
+                .map_err(|err| match err {
+                    WsError::Io(err) => err,
+                    err => io::Error::other(format!("WebSocket handshake failed: {err}")),
+                })
+        };
+        let (ws, _) = time::timeout(timeout, connect)
+            .await
+            .map_err(|_| options.timed_out())??;
+
+        Ok(Self::new(ws, websocket.telnet))
+    }
+}
+
+impl<S> WebSocketIo<S> {
+    pub fn new(ws: WebSocketStream<S>, binary: bool) -> Self {
+        Self {
+            ws,
+            incoming: Bytes::new(),
+            outgoing: BytesMut::new(),
+            binary,
+        }
+    }
+}
+
+fn into_io_error(err: WsError) -> io::Error {
+    match err {
+        WsError::Io(err) => err,
+        WsError::ConnectionClosed | WsError::AlreadyClosed => io::ErrorKind::NotConnected.into(),
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for WebSocketIo<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.incoming.is_empty() {
+            match ready!(self.ws.poll_next_unpin(cx)) {
+                Some(Ok(Message::Text(text))) => self.incoming = Bytes::from(text),
+                Some(Ok(Message::Binary(data))) => self.incoming = Bytes::from(data),
+
+                // EOF:
+                Some(Ok(Message::Close(_)) | Err(WsError::ConnectionClosed)) | None => {
+                    return Poll::Ready(Ok(()))
+                }
+
+                // Pings are answered for us; nothing else is interesting
+                Some(Ok(_)) => {}
+
+                Some(Err(err)) => return Poll::Ready(Err(into_io_error(err))),
+            }
+        }
+
+        let len = buf.remaining().min(self.incoming.len());
+        let chunk = self.incoming.split_to(len);
+        buf.put_slice(&chunk);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for WebSocketIo<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.outgoing.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.outgoing.is_empty() {
+            ready!(self.ws.poll_ready_unpin(cx)).map_err(into_io_error)?;
+
+            let frame = self.outgoing.split().freeze();
+            let message = match std::str::from_utf8(&frame) {
+                Ok(text) if !self.binary => Message::text(text),
+                _ => Message::binary(frame.to_vec()),
+            };
+            self.ws.start_send_unpin(message).map_err(into_io_error)?;
+        }
+
+        self.ws.poll_flush_unpin(cx).map_err(into_io_error)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        self.ws.poll_close_unpin(cx).map_err(into_io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+    use tokio_tungstenite::tungstenite::protocol::Role;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn frames_test() -> io::Result<()> {
+        let (local, remote) = duplex(4096);
+        let mut io = WebSocketIo::new(
+            WebSocketStream::from_raw_socket(local, Role::Client, None).await,
+            false,
+        );
+        let mut server = WebSocketStream::from_raw_socket(remote, Role::Server, None).await;
+
+        io.write_all(b"look").await?;
+        io.write_all(b" north").await?;
+        io.flush().await?;
+        assert_eq!(
+            server.next().await.unwrap().map_err(into_io_error)?,
+            Message::text("look north")
+        );
+
+        server
+            .send(Message::binary(b"You see a \xFFbutton".to_vec()))
+            .await
+            .map_err(into_io_error)?;
+        let mut read = [0u8; 32];
+        let len = io.read(&mut read).await?;
+        assert_eq!(&read[..len], b"You see a \xFFbutton");
+
+        server.close(None).await.map_err(into_io_error)?;
+        assert_eq!(io.read(&mut read).await?, 0);
+
+        Ok(())
+    }
+}